@@ -0,0 +1,43 @@
+//! Compare the node lookups of the full column scan and the point lookup of `raw_query` on the
+//! bundled DB, run with `cargo bench --bench raw_query`
+use std::time::Instant;
+
+use rocksdb::IteratorMode;
+use ssi::storage::{column_handle, columns, raw_query, setup_db_connection, Hash};
+
+fn main() {
+    let db = setup_db_connection(concat!(env!("CARGO_MANIFEST_DIR"), "/db"))
+        .expect("the bundled DB should be opened");
+    let h = column_handle(&db, columns::STATE).expect("the state column should be in DB");
+    let node_keys: Vec<Box<[u8]>> = db
+        .iterator_cf(h, IteratorMode::Start)
+        .map(|(k, _)| k)
+        .filter(|k| k.len() >= 32)
+        .step_by(10)
+        .take(100)
+        .collect();
+
+    let now = Instant::now();
+    for key in node_keys.iter() {
+        db.iterator_cf(h, IteratorMode::Start)
+            .find(|(k, _)| k == key)
+            .expect("the node should be in DB");
+    }
+    let scan = now.elapsed();
+
+    let now = Instant::now();
+    for key in node_keys.iter() {
+        let (prefix, hash) = key.split_at(key.len() - 32);
+        let mut node_key: Hash = Default::default();
+        node_key.copy_from_slice(hash);
+        raw_query(&db, (prefix, None), node_key).expect("the node should be in DB");
+    }
+    let lookup = now.elapsed();
+
+    println!(
+        "{} nodes, column scan: {:?}, point lookup: {:?}",
+        node_keys.len(),
+        scan,
+        lookup
+    );
+}
@@ -1,7 +1,7 @@
 /// Handle the options and arguments
 ///
 pub use clap::ArgMatches;
-use clap::{App, Arg};
+use clap::{App, Arg, SubCommand};
 use std::ffi::OsString;
 
 const VERSION: &str = env!("CARGO_PKG_VERSION");
@@ -19,6 +19,7 @@ where
                 .short("l")
                 .long("log")
                 .takes_value(true)
+                .global(true)
                 .number_of_values(1)
                 .help("Change the log level, accept level: all, trace, debug, info, warn, error[default]"),
         )
@@ -34,13 +35,6 @@ where
                 .long("all-node")
                 .help("Return the value in all type of node not only in the leaf node"),
         )
-        .arg(
-            Arg::with_name("root hash")
-                .short("r")
-                .long("root-hash")
-                .takes_value(true)
-                .help("The hash for trie root node, ex: 0x3b559d574c4a9f13e55d0256655f0f71a70a703766226f1080f80022e39c057d"),
-        )
         .arg(
             Arg::with_name("root hash diff")
                 .short("R")
@@ -49,17 +43,18 @@ where
                 .help("The hash for trie root node that you want to diff"),
         )
         .arg(
-            Arg::with_name("storage key")
-                .short("k")
-                .long("storage-key")
+            Arg::with_name("block diff")
+                .short("N")
+                .long("block-diff")
                 .takes_value(true)
-				.conflicts_with("pallet")
-				.conflicts_with("field")
-				.conflicts_with("twox 64 concat")
-				.conflicts_with("black2 128 concat")
-				.conflicts_with("twox 64 concat 2nd")
-				.conflicts_with("black2 128 concat 2nd")
-                .help("The storage key you want to inspect, it is okay to use only prefix part of storage key, ex: 6aa394eea5630e07c48ae0c9558cef7"),
+                .conflicts_with("root hash diff")
+                .help("The block number or block hash used to get the state root hash that you want to diff"),
+        )
+        .arg(
+            Arg::with_name("key diff")
+                .short("K")
+                .long("key-diff")
+                .help("Diff the storage keys and values instead of the trie nodes, the unchanged subtries are skipped"),
         )
         .arg(
             Arg::with_name("decode storage key")
@@ -68,91 +63,251 @@ where
                 .help("Decode the storage keys in stdin or file you want to inspect"),
         )
         .arg(
-            Arg::with_name("pallet")
-                .short("P")
-                .long("pallet")
+            Arg::with_name("metadata")
+                .short("m")
+                .long("metadata")
                 .takes_value(true)
-				.conflicts_with("storage key")
-                .help("The pallet name used for generate storage key you want to inspect, ex: System"),
+                .global(true)
+                .help("The runtime metadata file used to decode the storage values, ex: the result of `state_getMetadata` RPC call"),
         )
         .arg(
-            Arg::with_name("field")
-                .short("F")
-                .long("field")
+            Arg::with_name("types")
+                .short("y")
+                .long("types")
                 .takes_value(true)
-				.conflicts_with("storage key")
-                .help("The storage field name used for generate storage key you want to inspect, ex: Account"),
+                .global(true)
+                .requires("metadata")
+                .help("The JSON file of type definitions used to decode the storage values, ex: {\"AccountInfo\": {\"nonce\": \"Index\", \"refcount\": \"RefCount\", \"data\": \"AccountData\"}}"),
         )
         .arg(
-            Arg::with_name("twox 64 concat")
-                .short("T")
-                .long("twox-64-cat")
+            Arg::with_name("ss58 prefix")
+                .long("ss58-prefix")
                 .takes_value(true)
-				.conflicts_with("storage key")
-				.conflicts_with("black2 128 concat")
-				.conflicts_with("identity")
-                .help("The twox 64 hash and concated the key used for generate storage key you want to inspect"),
+                .global(true)
+                .help("The network prefix of SS58 address used in keys and outputs, ex: 0 (Polkadot), 2 (Kusama), 42 (Substrate)[default]"),
         )
         .arg(
-            Arg::with_name("black2 128 concat")
-                .short("B")
-                .long("blk2-128-cat")
+            Arg::with_name("summarize output")
+                .short("s")
+                .long("summarize")
+                .help("summarize the data of node to \"hash:{twox_hash_of_data}, length: {length}, Leaf: {true/false}\""),
+        )
+        .arg(
+            Arg::with_name("browse")
+                .long("browse")
+                .conflicts_with("decode storage key")
+                .help("Browse the trie node by node interactively from the state root, start from the node of storage key if it is given"),
+        )
+        .arg(
+            Arg::with_name("proof")
+                .long("proof")
+                .conflicts_with("browse")
+                .conflicts_with("decode storage key")
+                .help("Generate the storage proof of the storage keys at the state root, output the trie nodes and the SCALE encoded StorageProof in hex JSON"),
+        )
+        .arg(
+            Arg::with_name("verify proof")
+                .long("verify-proof")
                 .takes_value(true)
-				.conflicts_with("storage key")
-				.conflicts_with("twox 64 concat")
-				.conflicts_with("identity")
-                .help("The black2 128 hash and concated the key used for generate storage key you want to inspect"),
+                .conflicts_with("proof")
+                .conflicts_with("block")
+                .help("Verify the storage proof in the file without DB, and show the proven values of the storage keys, the file can be the output of proof, a JSON array of encoded trie nodes, or the SCALE encoded StorageProof, and the trusted state root hash must be given by -r"),
+        )
+        .arg(
+            Arg::with_name("check trie")
+                .long("check-trie")
+                .conflicts_with("proof")
+                .conflicts_with("browse")
+                .help("Verify the whole trie from the state root, re-hash every node and report the missing nodes and the nodes with incorrect hash"),
+        )
+        .arg(
+            Arg::with_name("extract code")
+                .long("extract-code")
+                .requires("output")
+                .conflicts_with("proof")
+                .conflicts_with("browse")
+                .conflicts_with("check trie")
+                .help("Extract the runtime wasm blob of :code at the state root to the output file, and show the hash, the length and the runtime version of the code"),
+        )
+        .arg(
+            Arg::with_name("export")
+                .long("export")
+                .conflicts_with("proof")
+                .conflicts_with("browse")
+                .conflicts_with("check trie")
+                .conflicts_with("extract code")
+                .help("Export the values at the state root as the genesis.raw storage of chain spec, {\"top\": {\"0x<key>\": \"0x<value>\"}, \"childrenDefault\": {...}}, the keys can be filtered by the prefix of storage key or pallet and field, and written to the output file if it is given"),
         )
         .arg(
-            Arg::with_name("identity")
-                .short("I")
-                .long("id")
+            Arg::with_name("format")
+                .long("format")
                 .takes_value(true)
+                .possible_values(&["json", "jsonl", "csv", "table", "hex", "dot"])
+                .help("The output format of inspect and diff results, json[default], jsonl (streaming one record per line), csv, table, hex (dump of the data), dot (Graphviz graph of the subtrie for inspect only)"),
+        )
+        .args(&state_args())
+        .args(&storage_key_args())
+        .arg(output_arg())
+        .arg(path_arg())
+        .subcommand(
+            SubCommand::with_name("extract-code")
+                .about("Extract the runtime wasm blob of :code at the state root to the output file, the same as --extract-code")
+                .args(&state_args())
+                .arg(output_arg().required(true))
+                .arg(path_arg().required(true)),
+        )
+        .subcommand(
+            SubCommand::with_name("proof")
+                .about("Generate the storage proof of the storage keys at the state root, the same as --proof")
+                .args(&state_args())
+                .args(&storage_key_args())
+                .arg(path_arg().required(true)),
+        )
+        .get_matches_from(itr)
+}
+
+/// The name and the matches of the subcommand if it is given, else the matches of the flags, the
+/// subcommands take the same options as the flags of their modes
+pub fn subcommand_matches(matches: ArgMatches) -> (Option<String>, ArgMatches) {
+    match matches.subcommand() {
+        (name, Some(sub)) => (Some(name.to_string()), sub.clone()),
+        _ => (None, matches),
+    }
+}
+
+/// The options of the state root hash, shared by the modes and the subcommands
+fn state_args<'a, 'b>() -> Vec<Arg<'a, 'b>> {
+    vec![
+        Arg::with_name("root hash")
+            .short("r")
+            .long("root-hash")
+            .alias("root")
+            .takes_value(true)
+            .help("The hash for trie root node, ex: 0x3b559d574c4a9f13e55d0256655f0f71a70a703766226f1080f80022e39c057d"),
+        Arg::with_name("block")
+            .short("n")
+            .long("block")
+            .takes_value(true)
+            .conflicts_with("root hash")
+            .help("The block number or block hash used to get the state root hash from DB, ex: 50 or 0xa5b3...(block hash)")
+    ]
+}
+
+/// The options to build the storage key, shared by the modes and the subcommands
+fn storage_key_args<'a, 'b>() -> Vec<Arg<'a, 'b>> {
+    vec![
+        Arg::with_name("storage key")
+            .short("k")
+            .long("storage-key")
+            .takes_value(true)
+            .multiple(true)
+            .number_of_values(1)
+				.conflicts_with("pallet")
+				.conflicts_with("field")
+				.conflicts_with("twox 64 concat")
+				.conflicts_with("black2 128 concat")
+				.conflicts_with("twox 64 concat 2nd")
+				.conflicts_with("black2 128 concat 2nd")
+            .help("The storage key you want to inspect, it is okay to use only prefix part of storage key, ex: 6aa394eea5630e07c48ae0c9558cef7, and it can be given multiple times only for proof and --verify-proof"),
+        Arg::with_name("pallet")
+            .short("P")
+            .long("pallet")
+            .takes_value(true)
+				.conflicts_with("storage key")
+            .help("The pallet name used for generate storage key you want to inspect, ex: System"),
+        Arg::with_name("field")
+            .short("F")
+            .long("field")
+            .takes_value(true)
+				.conflicts_with("storage key")
+            .help("The storage field name used for generate storage key you want to inspect, ex: Account"),
+        Arg::with_name("twox 64 concat")
+            .short("T")
+            .long("twox-64-cat")
+            .takes_value(true)
+				.conflicts_with("storage key")
+				.conflicts_with("black2 128 concat")
+				.conflicts_with("identity")
+            .help("The twox 64 hash and concated the key used for generate storage key you want to inspect"),
+        Arg::with_name("black2 128 concat")
+            .short("B")
+            .long("blk2-128-cat")
+            .takes_value(true)
+				.conflicts_with("storage key")
+				.conflicts_with("twox 64 concat")
+				.conflicts_with("identity")
+            .help("The black2 128 hash and concated the key used for generate storage key you want to inspect"),
+        Arg::with_name("identity")
+            .short("I")
+            .long("id")
+            .takes_value(true)
 				.conflicts_with("storage key")
 				.conflicts_with("twox 64 concat")
 				.conflicts_with("black2 128 concat")
-                .help("The identity key used for generate storage key you want to inspect"),
-        )
-        .arg(
-            Arg::with_name("twox 64 concat 2nd")
-                .short("t")
-                .long("twox-64-cat-2")
-                .takes_value(true)
+            .help("The identity key used for generate storage key you want to inspect"),
+        Arg::with_name("twox 64 concat 2nd")
+            .short("t")
+            .long("twox-64-cat-2")
+            .takes_value(true)
 				.conflicts_with("storage key")
 				.conflicts_with("black2 128 concat 2nd")
 				.conflicts_with("identity 2nd")
-                .help("The twox 64 hash and concated the 2nd key used for generate double map storage key you want to inspect"),
-        )
-        .arg(
-            Arg::with_name("black2 128 concat 2nd")
-                .short("b")
-                .long("blk2-128-cat-2")
-                .takes_value(true)
+            .help("The twox 64 hash and concated the 2nd key used for generate double map storage key you want to inspect"),
+        Arg::with_name("black2 128 concat 2nd")
+            .short("b")
+            .long("blk2-128-cat-2")
+            .takes_value(true)
 				.conflicts_with("storage key")
 				.conflicts_with("twox 64 concat 2nd")
 				.conflicts_with("identity 2nd")
-                .help("The black2 128 hash and concat the key use for generate double map storage key you want to inspect"),
-        )
-        .arg(
-            Arg::with_name("identity 2nd")
-                .short("i")
-                .long("id-2")
-                .takes_value(true)
+            .help("The black2 128 hash and concat the key use for generate double map storage key you want to inspect"),
+        Arg::with_name("identity 2nd")
+            .short("i")
+            .long("id-2")
+            .takes_value(true)
 				.conflicts_with("storage key")
 				.conflicts_with("twox 64 concat 2nd")
 				.conflicts_with("black2 128 concat 2nd")
-                .help("The identity key used for generate double map storage key you want to inspect"),
-        )
-        .arg(
-            Arg::with_name("summarize output")
-                .short("s")
-                .long("summarize")
-                .help("summarize the data of node to \"hash:{twox_hash_of_data}, length: {length}, Leaf: {true/false}\""),
-        )
-        .arg(
-            Arg::with_name("path")
-                .help("the db path to Rocks DB ot the file path to the log files")
-                .index(1)
-        )
-        .get_matches_from(itr)
+            .help("The identity key used for generate double map storage key you want to inspect"),
+        Arg::with_name("key")
+            .long("key")
+            .takes_value(true)
+				.conflicts_with("storage key")
+				.conflicts_with("twox 64 concat")
+				.conflicts_with("black2 128 concat")
+				.conflicts_with("identity")
+            .help("The key hashed by the hasher of `--hasher` option or the hasher in metadata used for generate storage key you want to inspect"),
+        Arg::with_name("hasher")
+            .long("hasher")
+            .takes_value(true)
+            .requires("key")
+            .help("The hasher for the key, accept: blake2_128, blake2_256, blake2_128_concat, twox_128, twox_256, twox_64_concat, identity"),
+        Arg::with_name("key 2nd")
+            .long("key-2")
+            .takes_value(true)
+				.conflicts_with("storage key")
+				.conflicts_with("twox 64 concat 2nd")
+				.conflicts_with("black2 128 concat 2nd")
+				.conflicts_with("identity 2nd")
+            .help("The 2nd key hashed by the hasher of `--hasher-2` option or the hasher in metadata used for generate double map storage key you want to inspect"),
+        Arg::with_name("hasher 2nd")
+            .long("hasher-2")
+            .takes_value(true)
+            .requires("key 2nd")
+            .help("The hasher for the 2nd key, accept the same hashers as `--hasher` option")
+    ]
+}
+
+fn output_arg<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("output")
+        .short("o")
+        .long("output")
+        .takes_value(true)
+        .help("The output file path")
+}
+
+fn path_arg<'a, 'b>() -> Arg<'a, 'b> {
+    Arg::with_name("path")
+        .help("the db path to Rocks DB ot the file path to the log files")
+        .index(1)
 }
@@ -3,9 +3,10 @@ use std::env;
 use std::fs::{read_dir, File};
 use std::io::{BufRead, BufReader};
 use std::path::Path;
+use std::sync::RwLock;
 
 use lazy_static::lazy_static;
-use sp_core::hashing::{blake2_128, twox_128};
+use sp_core::hashing::{blake2_128, blake2_256, twox_128, twox_256};
 
 use crate::logger::info;
 
@@ -685,7 +686,9 @@ lazy_static! {
 
         map
     };
-    pub static ref BLAKE2_MAP: HashMap<String, String> = {
+    /// The preimages of the keys hashed by the opaque hashers: blake2 128, blake2 256, twox 128,
+    /// and twox 256
+    pub static ref PREIMAGE_MAP: RwLock<HashMap<String, String>> = {
         let mut map = HashMap::new();
         let key_folder_reader = if let Ok(f) = env::var("SSI_KEY_FOLDER") {
             read_dir(Path::new(&f)).expect("Can not read SSI_KEY_FOLDER")
@@ -701,12 +704,11 @@ lazy_static! {
             let path = entry.unwrap().path();
             if let Some(ext) = path.extension() {
                 if ext == "b2" {
-                    info!("load blake2 128 hash key file: {:?}", path);
+                    info!("load preimage key file: {:?}", path);
                     if let Ok(f) = File::open(path.clone()) {
                         for line in BufReader::new(f).lines() {
                             if let Ok(key) = line {
-                                let h = hex::encode(blake2_128(key.trim().as_bytes()));
-                                map.insert(h, key.trim().to_string());
+                                insert_preimage(&mut map, key.trim());
                             }
                         }
                     } else {
@@ -715,6 +717,23 @@ lazy_static! {
                 }
             }
         }
-        map
+        RwLock::new(map)
     };
 }
+
+/// Insert the hashes of the key by the opaque hashers
+fn insert_preimage(map: &mut HashMap<String, String>, key: &str) {
+    let k = key.as_bytes();
+    map.insert(hex::encode(blake2_128(k)), key.to_string());
+    map.insert(hex::encode(blake2_256(k)), key.to_string());
+    map.insert(hex::encode(twox_128(k)), key.to_string());
+    map.insert(hex::encode(twox_256(k)), key.to_string());
+}
+
+/// Register the preimages of the opaque hashes besides the key files
+pub fn register_preimages(keys: &[&str]) {
+    let mut map = PREIMAGE_MAP.write().expect("preimages are not poisoned");
+    for key in keys.iter() {
+        insert_preimage(&mut map, key);
+    }
+}
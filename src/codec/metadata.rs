@@ -0,0 +1,366 @@
+/// Decode the runtime metadata and keep the storage entries for inspecting
+///
+/// The metadata is the SCALE encoded `RuntimeMetadataPrefixed` returned by the
+/// `state_getMetadata` RPC call, only V11 and V12 are supported.
+use std::collections::HashMap;
+use std::fs::read;
+use std::str::FromStr;
+use std::sync::RwLock;
+
+use codec::Decode;
+use lazy_static::lazy_static;
+use sp_core::hashing::twox_128;
+
+use crate::errors::Error;
+use crate::logger::{debug, info};
+
+/// The magic number `meta` in front of the runtime metadata
+const META_RESERVED: u32 = 0x6174_656d;
+
+lazy_static! {
+    /// The pallet prefixes keyed by the hex of twox 128 of pallet prefix
+    static ref PALLET_NAMES: RwLock<HashMap<String, String>> = RwLock::new(HashMap::new());
+    /// The storage entries keyed by the hex of twox 128 of pallet prefix and storage item name
+    static ref STORAGE_ENTRIES: RwLock<HashMap<String, StorageEntry>> = RwLock::new(HashMap::new());
+}
+
+#[allow(non_camel_case_types)]
+#[derive(Clone, Copy, Debug, PartialEq, Decode)]
+pub enum StorageHasher {
+    Blake2_128,
+    Blake2_256,
+    Blake2_128Concat,
+    Twox128,
+    Twox256,
+    Twox64Concat,
+    Identity,
+}
+
+impl StorageHasher {
+    /// The length of hash in hex, the concat hashers are followed by the key
+    pub fn hash_hex_len(self) -> usize {
+        match self {
+            StorageHasher::Blake2_128 | StorageHasher::Twox128 => 32,
+            StorageHasher::Blake2_256 | StorageHasher::Twox256 => 64,
+            StorageHasher::Blake2_128Concat => 32,
+            StorageHasher::Twox64Concat => 16,
+            StorageHasher::Identity => 0,
+        }
+    }
+
+    /// The key can not be decoded from the hash without preimage
+    pub fn is_opaque(self) -> bool {
+        match self {
+            StorageHasher::Blake2_128
+            | StorageHasher::Blake2_256
+            | StorageHasher::Twox128
+            | StorageHasher::Twox256 => true,
+            StorageHasher::Blake2_128Concat
+            | StorageHasher::Twox64Concat
+            | StorageHasher::Identity => false,
+        }
+    }
+}
+
+impl FromStr for StorageHasher {
+    type Err = Error;
+
+    /// Parse the hasher name, ex: blake2_128_concat or Blake2_128Concat
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().replace('_', "").as_str() {
+            "blake2128" => Ok(StorageHasher::Blake2_128),
+            "blake2256" => Ok(StorageHasher::Blake2_256),
+            "blake2128concat" => Ok(StorageHasher::Blake2_128Concat),
+            "twox128" => Ok(StorageHasher::Twox128),
+            "twox256" => Ok(StorageHasher::Twox256),
+            "twox64concat" => Ok(StorageHasher::Twox64Concat),
+            "identity" => Ok(StorageHasher::Identity),
+            _ => Err(Error::OptionValueIncorrect(
+                "hasher".to_string(),
+                format!("{} is not a storage hasher", s),
+            )),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Decode)]
+pub enum StorageEntryType {
+    Plain(String),
+    Map {
+        hasher: StorageHasher,
+        key: String,
+        value: String,
+        unused: bool,
+    },
+    DoubleMap {
+        hasher: StorageHasher,
+        key1: String,
+        key2: String,
+        value: String,
+        key2_hasher: StorageHasher,
+    },
+}
+
+impl StorageEntryType {
+    /// The type name of the storage value
+    pub fn value_type(&self) -> &str {
+        match self {
+            StorageEntryType::Plain(v) => v,
+            StorageEntryType::Map { value, .. } => value,
+            StorageEntryType::DoubleMap { value, .. } => value,
+        }
+    }
+}
+
+/// The storage entry described in runtime metadata
+#[derive(Clone, Debug, PartialEq)]
+pub struct StorageEntry {
+    pub pallet: String,
+    pub name: String,
+    pub ty: StorageEntryType,
+    pub default: Vec<u8>,
+}
+
+// The following types are only decoded to skip over the encoded metadata,
+// the fields not used for inspecting are never read.
+
+#[allow(dead_code)]
+#[derive(Decode)]
+enum StorageEntryModifier {
+    Optional,
+    Default,
+}
+
+#[allow(dead_code)]
+#[derive(Decode)]
+struct StorageEntryMetadata {
+    name: String,
+    modifier: StorageEntryModifier,
+    ty: StorageEntryType,
+    default: Vec<u8>,
+    documentation: Vec<String>,
+}
+
+#[derive(Decode)]
+struct StorageMetadata {
+    prefix: String,
+    entries: Vec<StorageEntryMetadata>,
+}
+
+#[allow(dead_code)]
+#[derive(Decode)]
+struct FunctionArgumentMetadata {
+    name: String,
+    ty: String,
+}
+
+#[allow(dead_code)]
+#[derive(Decode)]
+struct FunctionMetadata {
+    name: String,
+    arguments: Vec<FunctionArgumentMetadata>,
+    documentation: Vec<String>,
+}
+
+#[allow(dead_code)]
+#[derive(Decode)]
+struct EventMetadata {
+    name: String,
+    arguments: Vec<String>,
+    documentation: Vec<String>,
+}
+
+#[allow(dead_code)]
+#[derive(Decode)]
+struct ModuleConstantMetadata {
+    name: String,
+    ty: String,
+    value: Vec<u8>,
+    documentation: Vec<String>,
+}
+
+#[allow(dead_code)]
+#[derive(Decode)]
+struct ErrorMetadata {
+    name: String,
+    documentation: Vec<String>,
+}
+
+#[allow(dead_code)]
+#[derive(Decode)]
+struct ModuleMetadataV11 {
+    name: String,
+    storage: Option<StorageMetadata>,
+    calls: Option<Vec<FunctionMetadata>>,
+    event: Option<Vec<EventMetadata>>,
+    constants: Vec<ModuleConstantMetadata>,
+    errors: Vec<ErrorMetadata>,
+}
+
+#[allow(dead_code)]
+#[derive(Decode)]
+struct ModuleMetadataV12 {
+    name: String,
+    storage: Option<StorageMetadata>,
+    calls: Option<Vec<FunctionMetadata>>,
+    event: Option<Vec<EventMetadata>>,
+    constants: Vec<ModuleConstantMetadata>,
+    errors: Vec<ErrorMetadata>,
+    index: u8,
+}
+
+/// Get the metadata bytes from the file of binary, hex string or JSON RPC response
+fn read_metadata_bytes(file_path: &str) -> Result<Vec<u8>, Error> {
+    let raw = read(file_path)
+        .map_err(|e| Error::OptionValueIncorrect("metadata".to_string(), format!("{}", e)))?;
+    if raw.starts_with(&META_RESERVED.to_le_bytes()) {
+        return Ok(raw);
+    }
+    let text = String::from_utf8_lossy(&raw);
+    let hex_str = if let Some(idx) = text.find("0x6d657461") {
+        text[idx + 2..]
+            .split(|c: char| !c.is_ascii_hexdigit())
+            .next()
+            .unwrap_or_default()
+    } else {
+        text.trim().trim_start_matches("0x")
+    };
+    hex::decode(hex_str).map_err(|_| {
+        Error::OptionValueIncorrect(
+            "metadata".to_string(),
+            "the file is not SCALE encoded runtime metadata".to_string(),
+        )
+    })
+}
+
+/// Decode the storage entries from the SCALE encoded runtime metadata
+pub fn decode_storage_entries(data: &[u8]) -> Result<Vec<StorageEntry>, Error> {
+    let input = &mut &data[..];
+    let incorrect = |reason: String| Error::OptionValueIncorrect("metadata".to_string(), reason);
+    if u32::decode(input).ok() != Some(META_RESERVED) {
+        return Err(incorrect(
+            "magic number of metadata is not found".to_string(),
+        ));
+    }
+    let version = u8::decode(input).map_err(|e| incorrect(format!("{}", e)))?;
+    let storages = match version {
+        11 => Vec::<ModuleMetadataV11>::decode(input)
+            .map_err(|e| incorrect(format!("{}", e)))?
+            .into_iter()
+            .map(|m| m.storage)
+            .collect::<Vec<_>>(),
+        12 => Vec::<ModuleMetadataV12>::decode(input)
+            .map_err(|e| incorrect(format!("{}", e)))?
+            .into_iter()
+            .map(|m| m.storage)
+            .collect::<Vec<_>>(),
+        v => return Err(incorrect(format!("metadata V{} is not supported", v))),
+    };
+    let mut out = Vec::new();
+    for storage in storages.into_iter().flatten() {
+        for entry in storage.entries.into_iter() {
+            out.push(StorageEntry {
+                pallet: storage.prefix.clone(),
+                name: entry.name,
+                ty: entry.ty,
+                default: entry.default,
+            });
+        }
+    }
+    Ok(out)
+}
+
+/// Load the runtime metadata file, the pallet prefixes and storage entries are used to decode
+/// storage keys and storage values
+pub fn load_metadata(file_path: &str) -> Result<(), Error> {
+    let entries = decode_storage_entries(&read_metadata_bytes(file_path)?)?;
+    info!("Load {} storage entries from {}", entries.len(), file_path);
+    register_storage_entries(entries);
+    Ok(())
+}
+
+/// Build the tables of pallet prefixes and storage entries for the hashes in storage key
+pub fn register_storage_entries(entries: Vec<StorageEntry>) {
+    let mut pallet_names = PALLET_NAMES.write().expect("pallet names are not poisoned");
+    let mut map = STORAGE_ENTRIES
+        .write()
+        .expect("storage entries are not poisoned");
+    for entry in entries.into_iter() {
+        let mut key = hex::encode(twox_128(entry.pallet.as_bytes()));
+        pallet_names.insert(key.clone(), entry.pallet.clone());
+        key.push_str(&hex::encode(twox_128(entry.name.as_bytes())));
+        debug!("{} > {}: {}", entry.pallet, entry.name, key);
+        map.insert(key, entry);
+    }
+}
+
+/// Get the pallet prefix for the hex of twox 128 hash
+pub fn get_pallet_name(pallet_hash: &str) -> Option<String> {
+    PALLET_NAMES
+        .read()
+        .expect("pallet names are not poisoned")
+        .get(pallet_hash)
+        .cloned()
+}
+
+/// Get the storage entry for the storage key in hex
+pub fn get_storage_entry(storage_key: &str) -> Option<StorageEntry> {
+    storage_key.get(0..64).and_then(|k| {
+        STORAGE_ENTRIES
+            .read()
+            .expect("storage entries are not poisoned")
+            .get(k)
+            .cloned()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use codec::Encode;
+    #[test]
+    fn test_decode_storage_entries() {
+        let entry = (
+            "Account",
+            1u8,
+            (
+                1u8,
+                StorageHasher::Blake2_128Concat as u8,
+                "T::AccountId",
+                "AccountInfo<T::Index, T::AccountData>",
+                false,
+            ),
+            vec![0u8; 4],
+            Vec::<String>::new(),
+        );
+        let module = (
+            "System",
+            Some(("System", vec![entry])),
+            None::<()>,
+            None::<()>,
+            Vec::<()>::new(),
+            Vec::<()>::new(),
+            0u8,
+        );
+        let mut data = META_RESERVED.encode();
+        data.push(12);
+        data.extend(vec![module].encode());
+
+        let entries = decode_storage_entries(&data).unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].pallet, "System");
+        assert_eq!(entries[0].name, "Account");
+        assert_eq!(
+            entries[0].ty,
+            StorageEntryType::Map {
+                hasher: StorageHasher::Blake2_128Concat,
+                key: "T::AccountId".to_string(),
+                value: "AccountInfo<T::Index, T::AccountData>".to_string(),
+                unused: false,
+            }
+        );
+
+        data[4] = 10;
+        assert!(decode_storage_entries(&data).is_err());
+    }
+}
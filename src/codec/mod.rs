@@ -1,67 +1,205 @@
-use sp_core::hashing::{blake2_128, twox_64};
+use sp_core::hashing::{blake2_128, blake2_256, twox_128, twox_256, twox_64};
 
 mod hash_maps;
-use hash_maps::{BLAKE2_MAP, XX_MAP};
+pub use hash_maps::register_preimages;
+use hash_maps::{PREIMAGE_MAP, XX_MAP};
 
-pub fn twox_64_concat_encode(s: &str) -> String {
-    let mut out = hex::encode(twox_64(s.as_bytes()));
-    out.push_str(&hex::encode(s.as_bytes()));
+mod metadata;
+use metadata::{get_pallet_name, get_storage_entry, StorageEntryType};
+pub use metadata::{load_metadata, StorageHasher};
+
+mod types;
+use types::decode_value;
+pub use types::load_types;
+
+mod ss58;
+pub use ss58::set_ss58_prefix;
+use ss58::ss58_encode;
+
+mod typed_key;
+pub use typed_key::typed_key_encode;
+
+pub mod well_known;
+use well_known::{well_known_key_decode, well_known_value_decode};
+
+pub mod wasm;
+
+/// The hashers tried in order when the hasher of storage entry is unknown
+const GUESS_HASHERS: [StorageHasher; 7] = [
+    StorageHasher::Twox64Concat,
+    StorageHasher::Blake2_128Concat,
+    StorageHasher::Identity,
+    StorageHasher::Blake2_128,
+    StorageHasher::Blake2_256,
+    StorageHasher::Twox128,
+    StorageHasher::Twox256,
+];
+
+/// Hash the key with the storage hasher, and return the hex of the storage key part
+pub fn hashed_key_encode(hasher: StorageHasher, key: &[u8]) -> String {
+    let mut out = match hasher {
+        StorageHasher::Blake2_128 | StorageHasher::Blake2_128Concat => hex::encode(blake2_128(key)),
+        StorageHasher::Blake2_256 => hex::encode(blake2_256(key)),
+        StorageHasher::Twox128 => hex::encode(twox_128(key)),
+        StorageHasher::Twox256 => hex::encode(twox_256(key)),
+        StorageHasher::Twox64Concat => hex::encode(twox_64(key)),
+        StorageHasher::Identity => String::new(),
+    };
+    if !hasher.is_opaque() {
+        out.push_str(&hex::encode(key));
+    }
     out
 }
 
-pub fn blake2_128_concat_encode(s: &str) -> String {
-    let mut out = hex::encode(blake2_128(s.as_bytes()));
-    out.push_str(&hex::encode(s.as_bytes()));
-    out
+/// Look up the preimage of the hash from the key files
+fn preimage_lookup(hash: &str) -> Option<String> {
+    PREIMAGE_MAP
+        .read()
+        .expect("preimages are not poisoned")
+        .get(hash)
+        .or_else(|| XX_MAP.get(hash))
+        .cloned()
 }
 
-fn twox_64_concat_decode(s: String) -> Option<String> {
-    if s.len() > 16 {
-        let (first, last) = s.split_at(16);
-        let decode_bytes = hex::decode(last).unwrap_or_default();
-        let k = std::str::from_utf8(&decode_bytes).unwrap_or_default();
-        if twox_64_concat_encode(k).starts_with(first) {
-            return Some(k.to_string());
+fn is_readable(key: &[u8]) -> bool {
+    std::str::from_utf8(key)
+        .map(|k| !k.chars().any(char::is_control))
+        .unwrap_or(false)
+}
+
+/// Show the key decoded by the key type, or show the key as string if it is readable,
+/// else show the 32 bytes key as SS58 address, and show other keys in hex
+fn key_to_string(key: &[u8], key_type: Option<&str>) -> String {
+    if let Some(v) = key_type.and_then(|t| decode_value(t, key)) {
+        match v {
+            serde_json::Value::String(s) => s,
+            v => v.to_string(),
         }
+    } else if is_readable(key) {
+        String::from_utf8_lossy(key).to_string()
+    } else if let Some(address) = ss58_encode(key).filter(|_| key_type.is_none()) {
+        address
+    } else {
+        format!("0x{}", hex::encode(key))
     }
-    None
 }
 
-fn black2_128_concat_decode(s: String) -> Option<String> {
-    if s.len() > 32 {
-        let (first, last) = s.split_at(32);
-        let decode_bytes = hex::decode(last).unwrap_or_default();
-        let k = std::str::from_utf8(&decode_bytes).unwrap_or_default();
-        if blake2_128_concat_encode(k).starts_with(first) {
-            return Some(k.to_string());
+/// Decode the key hashed by the storage hasher at the beginning of the hex string, and return
+/// all the possible keys with the rest of hex string.
+/// The opaque hashes are decoded by the preimages in key files.
+/// The identity key can not be verified, so it should be readable when guessing the hasher.
+fn hashed_key_prefix_decode<'a>(
+    hasher: StorageHasher,
+    key_type: Option<&str>,
+    s: &'a str,
+    guess: bool,
+) -> Vec<(String, &'a str)> {
+    let hash_len = hasher.hash_hex_len();
+    if hasher.is_opaque() {
+        return s
+            .get(..hash_len)
+            .and_then(preimage_lookup)
+            .map(|k| vec![(k, &s[hash_len..])])
+            .unwrap_or_default();
+    }
+    if s.len() <= hash_len {
+        return Vec::new();
+    }
+    let (hash, key_hex) = s.split_at(hash_len);
+    let decode_bytes = hex::decode(key_hex).unwrap_or_default();
+    (1..=decode_bytes.len())
+        .filter_map(|end| {
+            let key = &decode_bytes[..end];
+            let matched = if hasher == StorageHasher::Identity {
+                !guess || is_readable(key)
+            } else {
+                hashed_key_encode(hasher, key).starts_with(hash)
+            };
+            if matched {
+                Some((key_to_string(key, key_type), &key_hex[end * 2..]))
+            } else {
+                None
+            }
+        })
+        .collect()
+}
+
+/// Decode the whole hex string as the key hashed by the storage hasher
+fn hashed_key_decode(
+    hasher: StorageHasher,
+    key_type: Option<&str>,
+    s: &str,
+    guess: bool,
+) -> Option<String> {
+    hashed_key_prefix_decode(hasher, key_type, s, guess)
+        .into_iter()
+        .find(|(_, rest)| rest.is_empty())
+        .map(|(k, _)| k)
+}
+
+/// Split the hex string into the first key and the second key of double map
+fn double_key_decode(
+    hashers: &[StorageHasher],
+    key2_hashers: &[StorageHasher],
+    key_types: (Option<&str>, Option<&str>),
+    s: &str,
+    guess: bool,
+) -> Option<(String, String)> {
+    for hasher in hashers.iter() {
+        for (k1, rest) in hashed_key_prefix_decode(*hasher, key_types.0, s, guess) {
+            for key2_hasher in key2_hashers.iter() {
+                // the boundary of two identity keys is unknown, skip the guess
+                if guess
+                    && *hasher == StorageHasher::Identity
+                    && *key2_hasher == StorageHasher::Identity
+                {
+                    continue;
+                }
+                if let Some(k2) = hashed_key_decode(*key2_hasher, key_types.1, rest, guess) {
+                    return Some((k1, k2));
+                }
+            }
         }
     }
     None
 }
 
-fn pallet_decode(s: &str) -> Option<&str> {
-    if let Some(p) = XX_MAP.get(s) {
-        Some(p)
-    } else {
-        None
-    }
+fn twox_64_concat_decode(s: String) -> Option<String> {
+    hashed_key_decode(StorageHasher::Twox64Concat, None, &s, true)
 }
 
-fn field_decode(s: &str) -> &str {
+fn black2_128_concat_decode(s: String) -> Option<String> {
+    hashed_key_decode(StorageHasher::Blake2_128Concat, None, &s, true)
+}
+
+fn pallet_decode(s: &str) -> Option<String> {
+    get_pallet_name(s).or_else(|| XX_MAP.get(s).cloned())
+}
+
+fn field_decode(s: &str) -> String {
     if let Some(p) = XX_MAP.get(s) {
-        p
+        p.to_string()
     } else {
-        s
+        s.to_string()
     }
 }
 
-// TODO: handle the 2nd key
+/// Decode the storage key to (pallet, field, key, 2nd key of double map),
+/// the well known key is decoded as the pallet, and the child id is decoded as the field
 pub fn storage_key_semantic_decode(
-    s: &'_ str,
+    s: &str,
     keep_unsolve: bool,
-) -> (Option<&'_ str>, Option<&'_ str>, Option<String>) {
+) -> (
+    Option<String>,
+    Option<String>,
+    Option<String>,
+    Option<String>,
+) {
+    if let Some((well_known_key, child_id)) = well_known_key_decode(s) {
+        return (Some(well_known_key), child_id, None, None);
+    }
     if s.len() < 32 {
-        return (None, None, None);
+        return (None, None, None, None);
     }
 
     let (p, tail) = s.split_at(32);
@@ -69,51 +207,116 @@ pub fn storage_key_semantic_decode(
 
     if tail.len() < 32 {
         if keep_unsolve {
-            return (pallet_name, Some(tail), None);
+            return (pallet_name, Some(tail.to_string()), None, None);
         } else {
-            return (pallet_name, None, None);
+            return (pallet_name, None, None, None);
         }
     }
 
+    let entry = get_storage_entry(s);
     let (f, tail) = tail.split_at(32);
-    let field_name = field_decode(f);
+    let field_name = entry
+        .as_ref()
+        .map(|e| e.name.clone())
+        .unwrap_or_else(|| field_decode(f));
 
-    let key = if !tail.is_empty() {
-        let mut k = twox_64_concat_decode(tail.to_string());
+    let (key, key2) = if !tail.is_empty() {
+        let mut k = match storage_entry_keys(s) {
+            Some(((hasher, key_type), None)) => {
+                hashed_key_decode(hasher, Some(&key_type), tail, false).map(|k| (k, None))
+            }
+            Some(((hasher, key_type), Some((key2_hasher, key2_type)))) => double_key_decode(
+                &[hasher],
+                &[key2_hasher],
+                (Some(&key_type), Some(&key2_type)),
+                tail,
+                false,
+            )
+            .map(|(k1, k2)| (k1, Some(k2))),
+            None => None,
+        };
 
         if k.is_none() {
-            k = black2_128_concat_decode(tail.to_string());
+            k = twox_64_concat_decode(tail.to_string()).map(|k| (k, None));
+        }
+        if k.is_none() {
+            k = black2_128_concat_decode(tail.to_string()).map(|k| (k, None));
         }
-        if k.is_none() && tail.len() >= 64 {
-            let (black2_key, tail) = tail.split_at(64);
-            k = BLAKE2_MAP
-                .get(black2_key)
-                .map(|c| format!("{}∥{}", c, tail));
+        if k.is_none() {
+            k = double_key_decode(&GUESS_HASHERS, &GUESS_HASHERS, (None, None), tail, true)
+                .map(|(k1, k2)| (k1, Some(k2)));
         }
-        if k.is_none() && tail.len() >= 32 && tail.len() < 64 {
-            let (two_x_key, tail) = tail.split_at(32);
-            k = XX_MAP.get(two_x_key).map(|c| format!("{}∥{}", c, tail));
+        if k.is_none() {
+            // the rest part of the opaque hash is not decodable
+            k = GUESS_HASHERS
+                .iter()
+                .filter(|h| h.is_opaque())
+                .flat_map(|h| hashed_key_prefix_decode(*h, None, tail, true))
+                .next()
+                .map(|(c, tail)| {
+                    if tail.is_empty() {
+                        (c, None)
+                    } else {
+                        (format!("{}∥{}", c, tail), None)
+                    }
+                });
         }
 
-        if k.is_some() {
-            k
-        } else if k.is_none() && keep_unsolve {
-            Some(tail.to_string())
+        if let Some((k1, k2)) = k {
+            (Some(k1), k2)
+        } else if keep_unsolve {
+            (Some(tail.to_string()), None)
         } else {
             let decode_bytes = hex::decode(tail).unwrap_or_default();
-            std::str::from_utf8(&decode_bytes)
-                .ok()
-                .map(|s| s.to_string())
+            (
+                std::str::from_utf8(&decode_bytes)
+                    .ok()
+                    .map(|s| s.to_string()),
+                None,
+            )
         }
     } else {
-        None
+        (None, None)
     };
 
-    (pallet_name, Some(field_name), key)
+    (pallet_name, Some(field_name), key, key2)
+}
+
+/// The hasher and the key type of a key in storage entry
+type EntryKey = (StorageHasher, String);
+
+/// Get the keys of the map or double map from the storage entry in the runtime metadata
+fn storage_entry_keys(storage_key: &str) -> Option<(EntryKey, Option<EntryKey>)> {
+    get_storage_entry(storage_key).and_then(|e| match e.ty {
+        StorageEntryType::Map { hasher, key, .. } => Some(((hasher, key), None)),
+        StorageEntryType::DoubleMap {
+            hasher,
+            key1,
+            key2,
+            key2_hasher,
+            ..
+        } => Some(((hasher, key1), Some((key2_hasher, key2)))),
+        StorageEntryType::Plain(_) => None,
+    })
+}
+
+/// Get the hashers of the map or double map from the storage entry in the runtime metadata
+pub fn storage_key_hashers(storage_key: &str) -> Option<(StorageHasher, Option<StorageHasher>)> {
+    storage_entry_keys(storage_key).map(|(k1, k2)| (k1.0, k2.map(|k| k.0)))
+}
+
+/// Decode the storage value by the value type of the storage entry in the runtime metadata,
+/// or the value of well known key
+pub fn storage_value_decode(storage_key: &str, data: &[u8]) -> Option<serde_json::Value> {
+    if let Some((well_known_key, _)) = well_known_key_decode(storage_key) {
+        return well_known_value_decode(&well_known_key, data);
+    }
+    get_storage_entry(storage_key).and_then(|e| decode_value(e.ty.value_type(), data))
 }
 
 #[cfg(test)]
 mod tests {
+    use super::metadata::{register_storage_entries, StorageEntry};
     use super::*;
     #[test]
     fn test_twox_64_concat_decode() {
@@ -126,6 +329,174 @@ mod tests {
     #[test]
     fn test_key_semantic_decode() {
         assert_eq!(storage_key_semantic_decode("26aa394eea5630e07c48ae0c9558cef7b99d880ec681799c0cf30e8886371da93fe5e3a3f34ce9df2f2f457665", false), 
-			(Some("System"), Some("Account"), Some("//Eve".to_string())));
+			(Some("System".to_string()), Some("Account".to_string()), Some("//Eve".to_string()), None));
+    }
+    #[test]
+    fn test_key_semantic_decode_with_metadata() {
+        register_storage_entries(vec![StorageEntry {
+            pallet: "TemplateModule".to_string(),
+            name: "Something".to_string(),
+            ty: StorageEntryType::Map {
+                hasher: StorageHasher::Identity,
+                key: "Vec<u8>".to_string(),
+                value: "u32".to_string(),
+                unused: false,
+            },
+            default: Vec::new(),
+        }]);
+        let mut key = hex::encode(sp_core::hashing::twox_128(b"TemplateModule"));
+        key.push_str(&hex::encode(sp_core::hashing::twox_128(b"Something")));
+        key.push_str(&hex::encode(b"ssi"));
+        assert_eq!(
+            storage_key_semantic_decode(&key, false),
+            (
+                Some("TemplateModule".to_string()),
+                Some("Something".to_string()),
+                Some("ssi".to_string()),
+                None
+            )
+        );
+    }
+    #[test]
+    fn test_double_map_key_semantic_decode() {
+        let mut key =
+            "26aa394eea5630e07c48ae0c9558cef7b99d880ec681799c0cf30e8886371da9".to_string();
+        key.push_str(&hashed_key_encode(StorageHasher::Twox64Concat, b"//Alice"));
+        key.push_str(&hashed_key_encode(
+            StorageHasher::Blake2_128Concat,
+            b"//Bob",
+        ));
+        assert_eq!(
+            storage_key_semantic_decode(&key, false),
+            (
+                Some("System".to_string()),
+                Some("Account".to_string()),
+                Some("//Alice".to_string()),
+                Some("//Bob".to_string())
+            )
+        );
+
+        key.truncate(64);
+        key.push_str(&hashed_key_encode(
+            StorageHasher::Blake2_128Concat,
+            b"//Alice",
+        ));
+        key.push_str(&hex::encode("//Bob"));
+        assert_eq!(
+            storage_key_semantic_decode(&key, false).3,
+            Some("//Bob".to_string())
+        );
+    }
+    #[test]
+    fn test_opaque_hasher_key_semantic_decode() {
+        assert_eq!(
+            hashed_key_encode(StorageHasher::Twox128, b"Account"),
+            "b99d880ec681799c0cf30e8886371da9"
+        );
+        assert_eq!(hashed_key_encode(StorageHasher::Identity, b"ssi"), "737369");
+        assert_eq!(
+            hashed_key_encode(StorageHasher::Blake2_256, b"").len(),
+            StorageHasher::Blake2_256.hash_hex_len()
+        );
+
+        // the preimage is only known from the registration
+        let preimage = "SsiOpaquePreimage";
+        let mut key = hashed_key_encode(StorageHasher::Twox128, b"System");
+        key.push_str(&hashed_key_encode(StorageHasher::Twox128, b"SsiOpaque"));
+        let blake2_key = format!(
+            "{}{}",
+            key,
+            hashed_key_encode(StorageHasher::Blake2_256, preimage.as_bytes())
+        );
+        let mut twox_key = format!(
+            "{}{}",
+            key,
+            hashed_key_encode(StorageHasher::Twox128, preimage.as_bytes())
+        );
+        assert_eq!(storage_key_semantic_decode(&blake2_key, false).2, None);
+
+        register_preimages(&[preimage]);
+        assert_eq!(
+            storage_key_semantic_decode(&blake2_key, false).2,
+            Some(preimage.to_string())
+        );
+        assert_eq!(
+            storage_key_semantic_decode(&twox_key, false).2,
+            Some(preimage.to_string())
+        );
+        twox_key.push_str(&hashed_key_encode(StorageHasher::Twox64Concat, b"//Eve"));
+        assert_eq!(
+            storage_key_semantic_decode(&twox_key, false),
+            (
+                Some("System".to_string()),
+                Some(hashed_key_encode(StorageHasher::Twox128, b"SsiOpaque")),
+                Some(preimage.to_string()),
+                Some("//Eve".to_string())
+            )
+        );
+    }
+    #[test]
+    fn test_typed_key_semantic_decode_with_metadata() {
+        register_storage_entries(vec![StorageEntry {
+            pallet: "Staking".to_string(),
+            name: "ErasStakers".to_string(),
+            ty: StorageEntryType::DoubleMap {
+                hasher: StorageHasher::Twox64Concat,
+                key1: "EraIndex".to_string(),
+                key2: "T::AccountId".to_string(),
+                value: "Exposure<T::AccountId, BalanceOf<T>>".to_string(),
+                key2_hasher: StorageHasher::Twox64Concat,
+            },
+            default: Vec::new(),
+        }]);
+        let account = "5GrwvaEF5zXb26Fz9rcQpDWS57CtERHpNehXCPcNoHGKutQY";
+        let mut key = hex::encode(sp_core::hashing::twox_128(b"Staking"));
+        key.push_str(&hex::encode(sp_core::hashing::twox_128(b"ErasStakers")));
+        key.push_str(&hashed_key_encode(
+            StorageHasher::Twox64Concat,
+            &typed_key_encode("u32:42").unwrap(),
+        ));
+        key.push_str(&hashed_key_encode(
+            StorageHasher::Twox64Concat,
+            &typed_key_encode(&format!("account:{}", account)).unwrap(),
+        ));
+        let result = storage_key_semantic_decode(&key, false);
+        assert_eq!(result.2, Some("42".to_string()));
+        assert_eq!(result.3, Some(account.to_string()));
+    }
+    #[test]
+    fn test_well_known_key_decode() {
+        assert_eq!(
+            storage_key_semantic_decode(&hex::encode(b":code"), false),
+            (Some(":code".to_string()), None, None, None)
+        );
+        assert_eq!(
+            storage_key_semantic_decode(&hex::encode(b":child_storage:default:contract"), true),
+            (
+                Some(":child_storage:default:".to_string()),
+                Some("contract".to_string()),
+                None,
+                None
+            )
+        );
+        assert_eq!(
+            storage_key_semantic_decode(&hex::encode(b":cod"), false),
+            (None, None, None, None)
+        );
+        assert_eq!(
+            storage_value_decode(&hex::encode(b":heappages"), &[8, 0, 0, 0, 0, 0, 0, 0]),
+            Some(serde_json::json!(8))
+        );
+        assert_eq!(
+            storage_value_decode(&hex::encode(b":changes_trie"), &[1, 4, 0, 0, 0, 2, 0, 0, 0]),
+            Some(serde_json::json!({"digest_interval": 4, "digest_levels": 2}))
+        );
+        assert_eq!(
+            storage_value_decode(&hex::encode(b":extrinsic_index"), &[1]),
+            None
+        );
+        let summary = storage_value_decode(&hex::encode(b":code"), &[0; 4]).unwrap();
+        assert_eq!(summary["length"], 4);
+        assert!(summary.get("runtime_version").is_none());
     }
 }
@@ -0,0 +1,44 @@
+/// Encode and decode the SS58 address of account id with the network prefix
+///
+/// The network prefix is 42 (Substrate) by default, and can be changed by `--ss58-prefix` option.
+use std::convert::TryFrom;
+
+use sp_core::crypto::{set_default_ss58_version, AccountId32, Ss58AddressFormat, Ss58Codec};
+
+use crate::errors::Error;
+
+/// Set the network prefix used to encode and decode SS58 addresses
+pub fn set_ss58_prefix(prefix: u8) {
+    set_default_ss58_version(
+        Ss58AddressFormat::try_from(prefix).unwrap_or(Ss58AddressFormat::Custom(prefix)),
+    );
+}
+
+/// Encode the 32 bytes account id to SS58 address
+pub fn ss58_encode(account: &[u8]) -> Option<String> {
+    <[u8; 32]>::try_from(account)
+        .ok()
+        .map(|a| AccountId32::from(a).to_ss58check())
+}
+
+/// Whether the string is a SS58 address of account id in any network prefix
+pub fn is_ss58_address(address: &str) -> bool {
+    AccountId32::from_ss58check_with_version(address).is_ok()
+}
+
+/// Decode the SS58 address to 32 bytes account id, the address should be in the network prefix
+pub fn ss58_decode(address: &str) -> Result<Vec<u8>, Error> {
+    let (account, format) = AccountId32::from_ss58check_with_version(address).map_err(|e| {
+        Error::OptionValueIncorrect(format!("address {}", address), format!("{:?}", e))
+    })?;
+    if format != Ss58AddressFormat::default() {
+        return Err(Error::OptionValueIncorrect(
+            format!("address {}", address),
+            format!(
+                "the network prefix is not {}",
+                u8::from(Ss58AddressFormat::default())
+            ),
+        ));
+    }
+    Ok(AsRef::<[u8]>::as_ref(&account).to_vec())
+}
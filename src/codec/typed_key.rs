@@ -0,0 +1,153 @@
+/// Encode the key arguments with type annotation
+///
+/// The typed key is SCALE encoded before hashing, ex: `u32:42`, `account:5GrwvaEF...`, `hex:0x1234`,
+/// and the tuple key is joined by comma, ex: `account:5GrwvaEF...,u64:1`.
+/// The argument without type annotation is used as the account id if it is a SS58 address in the
+/// network prefix, else it is used as the bytes of string, ex: `//Alice`.
+use codec::{Compact, Encode};
+
+use super::ss58::{is_ss58_address, ss58_decode};
+use crate::errors::Error;
+
+/// The type annotations of key arguments
+const KEY_TYPES: [&str; 10] = [
+    "u8", "u16", "u32", "u64", "u128", "bool", "str", "compact", "account", "hex",
+];
+
+fn incorrect(key: &str, reason: &str) -> Error {
+    Error::OptionValueIncorrect(format!("key {}", key), reason.to_string())
+}
+
+fn hex_decode(key: &str, v: &str) -> Result<Vec<u8>, Error> {
+    hex::decode(v.trim_start_matches("0x")).map_err(|e| incorrect(key, &format!("{}", e)))
+}
+
+/// SCALE encode the value of the key type
+fn encode_typed(ty: &str, v: &str) -> Result<Vec<u8>, Error> {
+    let key = format!("{}:{}", ty, v);
+    let not_number = |_| incorrect(&key, "the value is not a number in range");
+    match ty {
+        "u8" => Ok(v.parse::<u8>().map_err(not_number)?.encode()),
+        "u16" => Ok(v.parse::<u16>().map_err(not_number)?.encode()),
+        "u32" => Ok(v.parse::<u32>().map_err(not_number)?.encode()),
+        "u64" => Ok(v.parse::<u64>().map_err(not_number)?.encode()),
+        "u128" => Ok(v.parse::<u128>().map_err(not_number)?.encode()),
+        "compact" => Ok(Compact(v.parse::<u128>().map_err(not_number)?).encode()),
+        "bool" => Ok(v
+            .parse::<bool>()
+            .map_err(|_| incorrect(&key, "the value should be true or false"))?
+            .encode()),
+        "str" => Ok(v.encode()),
+        "account" => {
+            if v.starts_with("0x") {
+                let bytes = hex_decode(&key, v)?;
+                if bytes.len() != 32 {
+                    return Err(incorrect(&key, "the account id should be 32 bytes"));
+                }
+                Ok(bytes)
+            } else {
+                ss58_decode(v)
+            }
+        }
+        "hex" => hex_decode(&key, v),
+        _ => Err(incorrect(&key, "the type is not supported")),
+    }
+}
+
+/// Whether the part is annotated as `<type>:<value>`, the type is an identifier so the keys like
+/// `:code` are not annotated
+fn is_annotated(ty: &str, v: Option<&str>) -> bool {
+    v.is_some()
+        && ty.starts_with(|c: char| c.is_ascii_alphabetic())
+        && ty.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+}
+
+/// Encode the key argument, the argument is typed if any part joined by comma is annotated, and
+/// all the parts should be annotated by the key types, else the argument is used as SS58 address
+/// or the bytes of string
+pub fn typed_key_encode(s: &str) -> Result<Vec<u8>, Error> {
+    let parts = s
+        .split(',')
+        .map(|p| {
+            let mut iter = p.trim().splitn(2, ':');
+            (iter.next().unwrap_or_default(), iter.next())
+        })
+        .collect::<Vec<_>>();
+    if parts.iter().any(|(ty, v)| is_annotated(ty, *v)) {
+        let mut out = Vec::new();
+        for (ty, v) in parts.into_iter() {
+            match v {
+                Some(v) if KEY_TYPES.contains(&ty) => out.extend(encode_typed(ty, v)?),
+                Some(_) => {
+                    return Err(incorrect(
+                        s,
+                        &format!(
+                            "the type {} is unknown, the supported types are {}",
+                            ty,
+                            KEY_TYPES.join(", ")
+                        ),
+                    ))
+                }
+                None => {
+                    return Err(incorrect(
+                        s,
+                        &format!("the type annotation of {} is missing", ty),
+                    ))
+                }
+            }
+        }
+        Ok(out)
+    } else if is_ss58_address(s) {
+        // the address in other network prefix is rejected instead of being used as string
+        ss58_decode(s)
+    } else {
+        Ok(s.as_bytes().to_vec())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn test_typed_key_encode() {
+        assert_eq!(typed_key_encode("//Feride").unwrap(), b"//Feride".to_vec());
+        assert_eq!(typed_key_encode(":code").unwrap(), b":code".to_vec());
+        assert_eq!(typed_key_encode("u32:42").unwrap(), vec![42, 0, 0, 0]);
+        assert_eq!(
+            typed_key_encode("hex:0x0102, u16:1").unwrap(),
+            vec![1, 2, 1, 0]
+        );
+        assert_eq!(
+            typed_key_encode("str:ssi").unwrap(),
+            vec![12, 115, 115, 105]
+        );
+        assert_eq!(
+            typed_key_encode(
+                "account:0xd43593c715fdd31c61141abd04a99fd6822c8558854ccde39a5684e7a56da27d"
+            )
+            .unwrap()
+            .len(),
+            32
+        );
+        assert!(typed_key_encode("u8:256").is_err());
+        for key in ["u23:42", "acount:0x01", "hex:0x01, u6:1", "u8:1, 2"].iter() {
+            assert!(matches!(
+                typed_key_encode(key),
+                Err(Error::OptionValueIncorrect(..))
+            ));
+        }
+        match typed_key_encode("hex:0x01, u6:1") {
+            Err(Error::OptionValueIncorrect(_, reason)) => assert!(reason.contains("u6")),
+            _ => unreachable!(),
+        }
+        assert_eq!(
+            typed_key_encode("5GrwvaEF5zXb26Fz9rcQpDWS57CtERHpNehXCPcNoHGKutQY").unwrap(),
+            typed_key_encode(
+                "account:0xd43593c715fdd31c61141abd04a99fd6822c8558854ccde39a5684e7a56da27d"
+            )
+            .unwrap()
+        );
+        // Alice in the Polkadot network prefix
+        assert!(typed_key_encode("15oF4uVJwmo4TdGW7VfQxNLavjCXviqxT9S1MgbjMNHr6Sp5").is_err());
+    }
+}
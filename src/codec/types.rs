@@ -0,0 +1,363 @@
+/// Decode the SCALE encoded storage value by the type name in runtime metadata
+///
+/// The type definitions use the same JSON format of polkadot.js, the alias is a string, the struct
+/// is an object of fields, and the enum is an object with `_enum` field.  The common Substrate types
+/// are built in, and more definitions can be loaded from a JSON file.
+/// The account id is shown as SS58 address.
+use std::collections::HashMap;
+use std::fs::read_to_string;
+use std::sync::RwLock;
+
+use codec::{Compact, Decode};
+use lazy_static::lazy_static;
+use regex::Regex;
+use serde_json::{Map, Value};
+
+use super::ss58::ss58_encode;
+use crate::errors::Error;
+use crate::logger::{debug, info};
+
+/// The max nested level of type, prevent from the recursive type definitions
+const MAX_TYPE_DEPTH: usize = 64;
+
+lazy_static! {
+    static ref TYPE_DEFINITIONS: RwLock<HashMap<String, Value>> = {
+        let builtin: HashMap<String, Value> = serde_json::from_str(include_str!("types.json"))
+            .expect("builtin type definitions are valid JSON");
+        RwLock::new(builtin)
+    };
+    static ref TRAIT_PATH: Regex =
+        Regex::new(r"<T as [\w:]+(<I>)?>::|\bT::|\bI::").expect("trait path regex is valid");
+}
+
+/// Load the type definitions from JSON file, the definitions will overwrite the builtin ones
+pub fn load_types(file_path: &str) -> Result<(), Error> {
+    let content = read_to_string(file_path)?;
+    let types: HashMap<String, Value> = serde_json::from_str(&content)
+        .map_err(|e| Error::OptionValueIncorrect("types".to_string(), format!("{}", e)))?;
+    info!("Load {} type definitions from {}", types.len(), file_path);
+    TYPE_DEFINITIONS
+        .write()
+        .expect("type definitions are not poisoned")
+        .extend(types);
+    Ok(())
+}
+
+/// Split the string by the separator which is not in any bracket
+fn split_top_level(s: &str, sep: char) -> Vec<&str> {
+    let mut out = Vec::new();
+    let mut depth = 0;
+    let mut start = 0;
+    for (idx, c) in s.char_indices() {
+        match c {
+            '<' | '(' | '[' => depth += 1,
+            '>' | ')' | ']' => depth -= 1,
+            c if c == sep && depth == 0 => {
+                out.push(s[start..idx].trim());
+                start = idx + 1;
+            }
+            _ => (),
+        }
+    }
+    let last = s[start..].trim();
+    if !last.is_empty() {
+        out.push(last);
+    }
+    out
+}
+
+/// Split the type name and the generic parameters, ex: `Vec<(u32, u64)>` -> ("Vec", ["(u32, u64)"])
+fn split_generic(ty: &str) -> (&str, Vec<&str>) {
+    match ty.find('<') {
+        Some(idx) if ty.ends_with('>') => (
+            ty[..idx].trim(),
+            split_top_level(&ty[idx + 1..ty.len() - 1], ','),
+        ),
+        _ => (ty, Vec::new()),
+    }
+}
+
+fn decode_error(ty: &str, e: codec::Error) -> String {
+    format!("{} decode fail: {}", ty, e)
+}
+
+fn u128_to_value(v: u128) -> Value {
+    if v <= u64::MAX as u128 {
+        Value::from(v as u64)
+    } else {
+        Value::String(v.to_string())
+    }
+}
+
+fn i128_to_value(v: i128) -> Value {
+    if v <= i64::MAX as i128 && v >= i64::MIN as i128 {
+        Value::from(v as i64)
+    } else {
+        Value::String(v.to_string())
+    }
+}
+
+fn decode_bytes(len: usize, input: &mut &[u8]) -> Result<Value, String> {
+    if input.len() < len {
+        return Err(format!(
+            "{} bytes are expected, but {} left",
+            len,
+            input.len()
+        ));
+    }
+    let (bytes, rest) = input.split_at(len);
+    *input = rest;
+    Ok(Value::String(format!("0x{}", hex::encode(bytes))))
+}
+
+fn decode_len(input: &mut &[u8]) -> Result<usize, String> {
+    Compact::<u32>::decode(input)
+        .map(|l| l.0 as usize)
+        .map_err(|e| decode_error("length", e))
+}
+
+/// Decode the type defined in JSON
+fn decode_definition(
+    name: &str,
+    def: &Value,
+    input: &mut &[u8],
+    depth: usize,
+) -> Result<Value, String> {
+    match def {
+        Value::String(alias) => decode_type(alias, input, depth + 1),
+        Value::Object(fields) => {
+            if let Some(variants) = fields.get("_enum") {
+                let idx = u8::decode(input).map_err(|e| decode_error(name, e))? as usize;
+                match variants {
+                    Value::Array(names) => names
+                        .get(idx)
+                        .cloned()
+                        .ok_or_else(|| format!("{} has no variant {}", name, idx)),
+                    Value::Object(types) => {
+                        let (variant, ty) = types
+                            .iter()
+                            .nth(idx)
+                            .ok_or_else(|| format!("{} has no variant {}", name, idx))?;
+                        if ty.as_str() == Some("Null") {
+                            Ok(Value::String(variant.clone()))
+                        } else {
+                            let mut out = Map::new();
+                            out.insert(
+                                variant.clone(),
+                                decode_definition(variant, ty, input, depth + 1)?,
+                            );
+                            Ok(Value::Object(out))
+                        }
+                    }
+                    _ => Err(format!("enum definition of {} is not correct", name)),
+                }
+            } else {
+                let mut out = Map::new();
+                for (field, ty) in fields.iter() {
+                    out.insert(
+                        field.clone(),
+                        decode_definition(field, ty, input, depth + 1)?,
+                    );
+                }
+                Ok(Value::Object(out))
+            }
+        }
+        _ => Err(format!("definition of {} is not correct", name)),
+    }
+}
+
+/// Decode the type from the input, the decoded bytes are consumed from the input
+fn decode_type(ty: &str, input: &mut &[u8], depth: usize) -> Result<Value, String> {
+    if depth > MAX_TYPE_DEPTH {
+        return Err(format!("type {} is nested too deep", ty));
+    }
+    let ty = ty.trim();
+
+    if ty.starts_with('(') && ty.ends_with(')') {
+        let mut out = Vec::new();
+        for t in split_top_level(&ty[1..ty.len() - 1], ',') {
+            out.push(decode_type(t, input, depth + 1)?);
+        }
+        return Ok(if out.is_empty() {
+            Value::Null
+        } else {
+            Value::Array(out)
+        });
+    }
+
+    if ty.starts_with('[') && ty.ends_with(']') {
+        let parts = split_top_level(&ty[1..ty.len() - 1], ';');
+        if parts.len() != 2 {
+            return Err(format!("array type {} is not correct", ty));
+        }
+        let len: usize = parts[1]
+            .parse()
+            .map_err(|_| format!("array length of {} is not correct", ty))?;
+        if parts[0] == "u8" {
+            return decode_bytes(len, input);
+        }
+        let mut out = Vec::with_capacity(len);
+        for _ in 0..len {
+            out.push(decode_type(parts[0], input, depth + 1)?);
+        }
+        return Ok(Value::Array(out));
+    }
+
+    let (name, params) = split_generic(ty);
+    let param = |idx: usize| {
+        params
+            .get(idx)
+            .copied()
+            .ok_or_else(|| format!("type parameter of {} is missing", ty))
+    };
+    match name {
+        "Null" => Ok(Value::Null),
+        "bool" => bool::decode(input)
+            .map(Value::from)
+            .map_err(|e| decode_error(ty, e)),
+        "u8" => u8::decode(input)
+            .map(Value::from)
+            .map_err(|e| decode_error(ty, e)),
+        "u16" => u16::decode(input)
+            .map(Value::from)
+            .map_err(|e| decode_error(ty, e)),
+        "u32" => u32::decode(input)
+            .map(Value::from)
+            .map_err(|e| decode_error(ty, e)),
+        "u64" => u64::decode(input)
+            .map(Value::from)
+            .map_err(|e| decode_error(ty, e)),
+        "u128" => u128::decode(input)
+            .map(u128_to_value)
+            .map_err(|e| decode_error(ty, e)),
+        "i8" => i8::decode(input)
+            .map(Value::from)
+            .map_err(|e| decode_error(ty, e)),
+        "i16" => i16::decode(input)
+            .map(Value::from)
+            .map_err(|e| decode_error(ty, e)),
+        "i32" => i32::decode(input)
+            .map(Value::from)
+            .map_err(|e| decode_error(ty, e)),
+        "i64" => i64::decode(input)
+            .map(Value::from)
+            .map_err(|e| decode_error(ty, e)),
+        "i128" => i128::decode(input)
+            .map(i128_to_value)
+            .map_err(|e| decode_error(ty, e)),
+        "Compact" => Compact::<u128>::decode(input)
+            .map(|c| u128_to_value(c.0))
+            .map_err(|e| decode_error(ty, e)),
+        "AccountId32" => {
+            let account = <[u8; 32]>::decode(input).map_err(|e| decode_error(ty, e))?;
+            Ok(Value::String(ss58_encode(&account).unwrap_or_default()))
+        }
+        "H160" => decode_bytes(20, input),
+        "H256" => decode_bytes(32, input),
+        "H512" => decode_bytes(64, input),
+        "Bytes" => {
+            let len = decode_len(input)?;
+            decode_bytes(len, input)
+        }
+        "Text" | "String" => Vec::<u8>::decode(input)
+            .map(|s| Value::String(String::from_utf8_lossy(&s).to_string()))
+            .map_err(|e| decode_error(ty, e)),
+        "Box" | "Rc" | "Arc" => decode_type(param(0)?, input, depth + 1),
+        "PhantomData" => Ok(Value::Null),
+        "Option" => match (
+            u8::decode(input).map_err(|e| decode_error(ty, e))?,
+            param(0)?,
+        ) {
+            (0, _) => Ok(Value::Null),
+            // `Option<bool>` is encoded in one byte
+            (1, "bool") => Ok(Value::Bool(true)),
+            (2, "bool") => Ok(Value::Bool(false)),
+            (1, inner) => decode_type(inner, input, depth + 1),
+            _ => Err(format!("{} is not a valid option", ty)),
+        },
+        "Vec" | "VecDeque" | "BTreeSet" => {
+            let len = decode_len(input)?;
+            let item_type = param(0)?;
+            if item_type == "u8" {
+                return decode_bytes(len, input);
+            }
+            let mut out = Vec::new();
+            for _ in 0..len {
+                out.push(decode_type(item_type, input, depth + 1)?);
+            }
+            Ok(Value::Array(out))
+        }
+        "BTreeMap" => {
+            let len = decode_len(input)?;
+            let (key_type, value_type) = (param(0)?, param(1)?);
+            let mut out = Vec::new();
+            for _ in 0..len {
+                out.push(Value::Array(vec![
+                    decode_type(key_type, input, depth + 1)?,
+                    decode_type(value_type, input, depth + 1)?,
+                ]));
+            }
+            Ok(Value::Array(out))
+        }
+        _ => {
+            let def = TYPE_DEFINITIONS
+                .read()
+                .expect("type definitions are not poisoned")
+                .get(name)
+                .cloned()
+                .ok_or_else(|| format!("type {} is not defined", name))?;
+            decode_definition(name, &def, input, depth)
+        }
+    }
+}
+
+/// Decode the data by the type name, all the data should be consumed
+pub fn decode_value(ty: &str, data: &[u8]) -> Option<Value> {
+    let ty = TRAIT_PATH.replace_all(ty, "");
+    let mut input = data;
+    match decode_type(&ty, &mut input, 0) {
+        Ok(v) if input.is_empty() => Some(v),
+        Ok(_) => {
+            debug!("{} bytes are left after decoding {}", input.len(), ty);
+            None
+        }
+        Err(e) => {
+            debug!("{}", e);
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn test_decode_account_info() {
+        let data = hex::decode("00000000010000000000a0dec5adc9353600000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000000").unwrap();
+        assert_eq!(
+            decode_value("AccountInfo<T::Index, T::AccountData>", &data)
+                .unwrap()
+                .to_string(),
+            r#"{"nonce":0,"refcount":1,"data":{"free":"1000000000000000000000","reserved":0,"misc_frozen":0,"fee_frozen":0}}"#
+        );
+        assert_eq!(
+            decode_value("AccountInfo<T::Index, T::AccountData>", &data[1..]),
+            None
+        );
+    }
+    #[test]
+    fn test_decode_generic_types() {
+        assert_eq!(
+            decode_value("Vec<(T::BlockNumber, Option<bool>)>", &[4, 5, 0, 0, 0, 1])
+                .unwrap()
+                .to_string(),
+            "[[5,true]]"
+        );
+        assert_eq!(
+            decode_value("<T as Trait>::Moment", &[1, 0, 0, 0, 0, 0, 0, 0])
+                .unwrap()
+                .to_string(),
+            "1"
+        );
+    }
+}
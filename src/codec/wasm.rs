@@ -0,0 +1,178 @@
+/// Read the runtime wasm blob stored at `:code`
+///
+/// The runtime version is embedded by the runtime in the `runtime_version` custom section of
+/// the wasm blob as the SCALE encoded `RuntimeVersion`.
+use codec::Decode;
+use serde::Serialize;
+use serde_json::{json, Value};
+use sp_core::hashing::blake2_256;
+
+/// The magic number and version at the beginning of a wasm module
+const WASM_HEADER: [u8; 8] = [0x00, 0x61, 0x73, 0x6d, 0x01, 0x00, 0x00, 0x00];
+
+/// The magic number of the zstd compressed code blob
+const COMPRESSED_CODE_MAGIC: [u8; 8] = [0x52, 0xbc, 0x53, 0x76, 0x46, 0xdb, 0x8e, 0x05];
+
+/// The name of the custom section keeping the runtime version
+const RUNTIME_VERSION_SECTION: &str = "runtime_version";
+
+/// The runtime version embedded in the runtime wasm blob
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct RuntimeVersion {
+    pub spec_name: String,
+    pub impl_name: String,
+    pub authoring_version: u32,
+    pub spec_version: u32,
+    pub impl_version: u32,
+    /// The transaction version is not in the runtime version of the old runtimes
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub transaction_version: Option<u32>,
+}
+
+/// The fields of runtime version in the order of SCALE encoding
+#[derive(Decode)]
+struct RawRuntimeVersion {
+    spec_name: Vec<u8>,
+    impl_name: Vec<u8>,
+    authoring_version: u32,
+    spec_version: u32,
+    impl_version: u32,
+    _apis: Vec<([u8; 8], u32)>,
+}
+
+/// Read the unsigned LEB128 integer, and move the input after it
+fn read_leb128(input: &mut &[u8]) -> Option<usize> {
+    let mut value: usize = 0;
+    for shift in (0..35).step_by(7) {
+        let (byte, rest) = input.split_first()?;
+        *input = rest;
+        value |= ((byte & 0x7f) as usize) << shift;
+        if byte & 0x80 == 0 {
+            return Some(value);
+        }
+    }
+    None
+}
+
+/// Whether the code blob is compressed, the compressed code can not be read without
+/// decompression
+pub fn is_compressed_code(code: &[u8]) -> bool {
+    code.starts_with(&COMPRESSED_CODE_MAGIC)
+}
+
+/// Get the payload of the custom section by name from the wasm module
+pub fn wasm_custom_section<'a>(code: &'a [u8], name: &str) -> Option<&'a [u8]> {
+    let mut input = code.strip_prefix(&WASM_HEADER[..])?;
+    while let Some((id, rest)) = input.split_first() {
+        input = rest;
+        let size = read_leb128(&mut input)?;
+        let section = input.get(..size)?;
+        input = &input[size..];
+        if *id == 0 {
+            let mut section = section;
+            let name_len = read_leb128(&mut section)?;
+            if section.get(..name_len)? == name.as_bytes() {
+                return Some(&section[name_len..]);
+            }
+        }
+    }
+    None
+}
+
+/// Get the runtime version from the `runtime_version` custom section of the runtime wasm blob
+pub fn runtime_version(code: &[u8]) -> Option<RuntimeVersion> {
+    let mut input = wasm_custom_section(code, RUNTIME_VERSION_SECTION)?;
+    let raw = RawRuntimeVersion::decode(&mut input).ok()?;
+    Some(RuntimeVersion {
+        spec_name: String::from_utf8_lossy(&raw.spec_name).to_string(),
+        impl_name: String::from_utf8_lossy(&raw.impl_name).to_string(),
+        authoring_version: raw.authoring_version,
+        spec_version: raw.spec_version,
+        impl_version: raw.impl_version,
+        transaction_version: u32::decode(&mut input).ok(),
+    })
+}
+
+/// Summarize the runtime code with the length, the blake2 256 hash and the runtime version if it
+/// can be found, instead of the whole blob
+pub fn code_summary(code: &[u8]) -> Value {
+    let mut summary = json!({
+        "length": code.len(),
+        "hash": format!("0x{}", hex::encode(blake2_256(code))),
+    });
+    if is_compressed_code(code) {
+        summary["compressed"] = Value::Bool(true);
+    } else if let Some(version) = runtime_version(code) {
+        summary["runtime_version"] = serde_json::to_value(version).unwrap_or_default();
+    }
+    summary
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use codec::Encode;
+
+    /// The unsigned LEB128 integer
+    fn leb128(mut value: usize) -> Vec<u8> {
+        let mut out = Vec::new();
+        loop {
+            let byte = (value & 0x7f) as u8;
+            value >>= 7;
+            if value == 0 {
+                out.push(byte);
+                return out;
+            }
+            out.push(byte | 0x80);
+        }
+    }
+
+    /// A wasm module with an empty type section and a custom section
+    pub(crate) fn sample_code(section_name: &str, payload: &[u8]) -> Vec<u8> {
+        let mut section = leb128(section_name.len());
+        section.extend_from_slice(section_name.as_bytes());
+        section.extend_from_slice(payload);
+        let mut code = WASM_HEADER.to_vec();
+        code.extend_from_slice(&[0x01, 0x01, 0x00]);
+        code.push(0);
+        code.extend(leb128(section.len()));
+        code.extend(section);
+        code
+    }
+
+    /// The SCALE encoded runtime version
+    pub(crate) fn sample_version() -> Vec<u8> {
+        let mut version = (b"node".to_vec(), b"substrate-node".to_vec(), 10u32).encode();
+        version.extend((265u32, 1u32).encode());
+        version.extend(vec![([1u8; 8], 3u32)].encode());
+        version.extend(2u32.encode());
+        version
+    }
+
+    #[test]
+    fn test_runtime_version() {
+        let mut input = &[0xe5, 0x8e, 0x26, 0xff][..];
+        assert_eq!(read_leb128(&mut input), Some(624_485));
+        assert_eq!(input, &[0xff]);
+
+        let code = sample_code(RUNTIME_VERSION_SECTION, &sample_version());
+        assert_eq!(
+            runtime_version(&code),
+            Some(RuntimeVersion {
+                spec_name: "node".to_string(),
+                impl_name: "substrate-node".to_string(),
+                authoring_version: 10,
+                spec_version: 265,
+                impl_version: 1,
+                transaction_version: Some(2),
+            })
+        );
+        assert_eq!(code_summary(&code)["runtime_version"]["spec_version"], 265);
+        assert!(runtime_version(&sample_code("name", &sample_version())).is_none());
+        assert!(runtime_version(&code[8..]).is_none());
+
+        let mut compressed = COMPRESSED_CODE_MAGIC.to_vec();
+        compressed.extend_from_slice(&code);
+        assert_eq!(code_summary(&compressed)["compressed"], true);
+    }
+}
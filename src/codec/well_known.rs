@@ -0,0 +1,76 @@
+/// The well known storage keys
+///
+/// The keys are set by the client or the executive directly in the main trie without the
+/// twox128 hashes of pallet and field, so they are matched by the raw bytes of storage key.
+use codec::Decode;
+use serde_json::{json, Value};
+
+use super::is_readable;
+use super::wasm::code_summary;
+
+/// The runtime wasm blob
+pub const CODE: &[u8] = b":code";
+/// The number of memory pages for the runtime
+pub const HEAP_PAGES: &[u8] = b":heappages";
+/// The index of current extrinsic in the block
+pub const EXTRINSIC_INDEX: &[u8] = b":extrinsic_index";
+/// The configuration of changes trie
+pub const CHANGES_TRIE_CONFIG: &[u8] = b":changes_trie";
+/// The key prefix of the child trie roots, followed by the child trie type and the child id
+pub const CHILD_STORAGE_KEY_PREFIX: &[u8] = b":child_storage:";
+/// The key prefix of the default child trie roots, followed by the child id
+pub const DEFAULT_CHILD_STORAGE_KEY_PREFIX: &[u8] = b":child_storage:default:";
+
+const WELL_KNOWN_KEYS: [&[u8]; 4] = [CODE, HEAP_PAGES, EXTRINSIC_INDEX, CHANGES_TRIE_CONFIG];
+
+/// The longer prefix is matched first
+const WELL_KNOWN_PREFIXES: [&[u8]; 2] =
+    [DEFAULT_CHILD_STORAGE_KEY_PREFIX, CHILD_STORAGE_KEY_PREFIX];
+
+/// The configuration of changes trie
+#[derive(Decode)]
+struct ChangesTrieConfiguration {
+    digest_interval: u32,
+    digest_levels: u32,
+}
+
+/// Decode the storage key in hex to the well known key, and the child id if the key is a child
+/// trie root
+pub fn well_known_key_decode(s: &str) -> Option<(String, Option<String>)> {
+    let key = hex::decode(s).ok()?;
+    if let Some(k) = WELL_KNOWN_KEYS.iter().find(|k| **k == key.as_slice()) {
+        return Some((String::from_utf8_lossy(k).to_string(), None));
+    }
+    WELL_KNOWN_PREFIXES.iter().find_map(|p| {
+        let child_id = key.strip_prefix(*p).filter(|id| !id.is_empty())?;
+        let child_id = if is_readable(child_id) {
+            String::from_utf8_lossy(child_id).to_string()
+        } else {
+            format!("0x{}", hex::encode(child_id))
+        };
+        Some((String::from_utf8_lossy(p).to_string(), Some(child_id)))
+    })
+}
+
+/// Decode the value of the well known key, the runtime code is summarized
+pub fn well_known_value_decode(key: &str, data: &[u8]) -> Option<Value> {
+    let mut input = data;
+    let value = match key.as_bytes() {
+        CODE => return Some(code_summary(data)),
+        HEAP_PAGES => json!(u64::decode(&mut input).ok()?),
+        EXTRINSIC_INDEX => json!(u32::decode(&mut input).ok()?),
+        CHANGES_TRIE_CONFIG => match Option::<ChangesTrieConfiguration>::decode(&mut input).ok()? {
+            Some(c) => json!({
+                "digest_interval": c.digest_interval,
+                "digest_levels": c.digest_levels,
+            }),
+            None => Value::Null,
+        },
+        _ => return None,
+    };
+    if input.is_empty() {
+        Some(value)
+    } else {
+        None
+    }
+}
@@ -7,6 +7,10 @@ use std::io::Error as IOError;
 pub enum Error {
     #[fail(display = "Options {} is not correct, due to {}", 0, 1)]
     OptionValueIncorrect(String, String),
+    #[fail(display = "Block {} is not found in DB", 0)]
+    BlockNotFound(String),
+    #[fail(display = "Data of {} in DB is not correct, due to {}", 0, 1)]
+    DBDataIncorrect(String, String),
 }
 
 impl From<FromHexError> for Error {
@@ -20,3 +24,9 @@ impl From<IOError> for Error {
         Error::OptionValueIncorrect("path error".to_string(), format!("{}", e))
     }
 }
+
+impl From<serde_json::Error> for Error {
+    fn from(e: serde_json::Error) -> Self {
+        Error::OptionValueIncorrect("output".to_string(), format!("{}", e))
+    }
+}
@@ -0,0 +1,347 @@
+/// Browse the trie node by node in the terminal
+///
+/// The current node is shown with the node kind, the partial nibbles, the value and the 16 child
+/// slots, and the semantic decode of the current path is shown at the top.
+/// The commands are read line by line:
+/// - `0`..`f`: step into the child in the slot
+/// - `>`: step into the child of extension node
+/// - `..` or `u`: step out to the parent node
+/// - `r`: go back to the root node
+/// - `q`: quit
+use std::io::{self, BufRead, Write};
+
+use colored::*;
+use hash_db::HashDBRef;
+
+use crate::cli::ArgMatches;
+use crate::codec::storage_value_decode;
+use crate::errors::Error;
+use crate::inspector::db::get_state_root_hash;
+use crate::inspector::get_storage_key_hash;
+use crate::inspector::record::SemanticKey;
+use crate::logger::info;
+use crate::storage::{
+    load_trie_node, map_char_to_pos, map_pos_to_char, nibbles_to_hex, setup_db_connection, Hasher,
+    NodeKind, NodeRef, SimpleTrie, TrieNode,
+};
+
+/// The bytes of value shown in the node view
+const VALUE_PREVIEW_BYTES: usize = 32;
+
+/// A visited node with the nibble path to the node
+struct Frame {
+    node_ref: NodeRef,
+    path: Vec<u8>,
+    node: TrieNode,
+}
+
+/// The browser keeps the nodes from the root to the current node
+pub struct TrieBrowser<'a> {
+    db: &'a dyn HashDBRef<Hasher, Vec<u8>>,
+    frames: Vec<Frame>,
+}
+
+impl<'a> TrieBrowser<'a> {
+    pub fn new(
+        db: &'a dyn HashDBRef<Hasher, Vec<u8>>,
+        state_root_hash: [u8; 32],
+    ) -> Result<Self, Error> {
+        let node_ref = NodeRef::Hash(state_root_hash);
+        let node = load_trie_node(db, &node_ref, &[])?;
+        Ok(TrieBrowser {
+            db,
+            frames: vec![Frame {
+                node_ref,
+                path: Vec::new(),
+                node,
+            }],
+        })
+    }
+
+    fn current(&self) -> &Frame {
+        self.frames.last().expect("the root node is always kept")
+    }
+
+    /// The nibble path of the current node including its partial nibbles
+    pub fn node_path(&self) -> Vec<u8> {
+        let frame = self.current();
+        let mut path = frame.path.clone();
+        path.extend_from_slice(&frame.node.partial);
+        path
+    }
+
+    pub fn node_kind(&self) -> NodeKind {
+        self.current().node.kind
+    }
+
+    /// Step into the child in the slot of current node
+    pub fn step_into(&mut self, slot: usize) -> Result<(), Error> {
+        let child = self
+            .current()
+            .node
+            .children
+            .get(slot)
+            .cloned()
+            .flatten()
+            .ok_or_else(|| {
+                Error::OptionValueIncorrect(
+                    format!("child {}", map_pos_to_char(slot)),
+                    "the child slot is empty".to_string(),
+                )
+            })?;
+        let mut path = self.node_path();
+        path.push(slot as u8);
+        self.push_child(child, path)
+    }
+
+    /// Step into the child of current extension node
+    pub fn step_into_extension(&mut self) -> Result<(), Error> {
+        let child = self.current().node.extension_child.clone().ok_or_else(|| {
+            Error::OptionValueIncorrect(
+                "child >".to_string(),
+                "the node is not an extension node".to_string(),
+            )
+        })?;
+        let path = self.node_path();
+        self.push_child(child, path)
+    }
+
+    fn push_child(&mut self, child: NodeRef, path: Vec<u8>) -> Result<(), Error> {
+        let node = load_trie_node(self.db, &child, &path)?;
+        self.frames.push(Frame {
+            node_ref: child,
+            path,
+            node,
+        });
+        Ok(())
+    }
+
+    /// Step out to the parent node, false if the current node is the root
+    pub fn step_out(&mut self) -> bool {
+        if self.frames.len() > 1 {
+            self.frames.pop();
+            true
+        } else {
+            false
+        }
+    }
+
+    pub fn back_to_root(&mut self) {
+        self.frames.truncate(1);
+    }
+
+    /// Step into the children along the nibble path as far as the path matches
+    pub fn walk(&mut self, path: &[u8]) -> Result<(), Error> {
+        loop {
+            let node_path = self.node_path();
+            if node_path.len() >= path.len() || !path.starts_with(&node_path) {
+                return Ok(());
+            }
+            let stepped = if self.current().node.extension_child.is_some() {
+                self.step_into_extension()
+            } else {
+                self.step_into(path[node_path.len()] as usize)
+            };
+            if stepped.is_err() {
+                return Ok(());
+            }
+        }
+    }
+
+    /// Show the current node
+    pub fn render(&self) -> String {
+        let frame = self.current();
+        let node_path = nibbles_to_hex(&self.node_path());
+        let semantic = SemanticKey::decode(&node_path);
+        let mut out = String::new();
+        out.push_str(&format!(
+            "{} {}\n",
+            "Path:".bold(),
+            if semantic.pallet.is_empty() {
+                "-".to_string()
+            } else {
+                semantic.to_string()
+            }
+            .green()
+        ));
+        out.push_str(&format!("      0x{}\n", node_path));
+        out.push_str(&format!(
+            "{} {:?}  (depth {})\n",
+            "Node:".bold(),
+            frame.node.kind,
+            self.frames.len() - 1
+        ));
+        out.push_str(&match &frame.node_ref {
+            NodeRef::Hash(h) => format!("      hash 0x{}\n", hex::encode(h)),
+            NodeRef::Inline(d) => format!("      inline {} bytes\n", d.len()),
+        });
+        out.push_str(&format!(
+            "{} {}\n",
+            "Partial:".bold(),
+            nibbles_to_hex(&frame.node.partial)
+        ));
+        if let Some(v) = &frame.node.value {
+            let preview = if v.len() > VALUE_PREVIEW_BYTES {
+                format!("0x{}...", hex::encode(&v[..VALUE_PREVIEW_BYTES]))
+            } else {
+                format!("0x{}", hex::encode(v))
+            };
+            out.push_str(&format!(
+                "{} {} bytes {}\n",
+                "Value:".bold(),
+                v.len(),
+                preview
+            ));
+            if let Some(decoded) = storage_value_decode(&node_path, v) {
+                out.push_str(&format!("      {}\n", decoded));
+            }
+        }
+        if !frame.node.children.is_empty() {
+            out.push_str(&format!("{}\n", "Children:".bold()));
+            for (idx, child) in frame.node.children.iter().enumerate() {
+                let slot = format!("[{}]", map_pos_to_char(idx));
+                match child {
+                    Some(NodeRef::Hash(h)) => {
+                        out.push_str(&format!("  {} hash 0x{}\n", slot.cyan(), hex::encode(h)))
+                    }
+                    Some(NodeRef::Inline(d)) => {
+                        out.push_str(&format!("  {} inline {} bytes\n", slot.cyan(), d.len()))
+                    }
+                    None => out.push_str(&format!("  {} -\n", slot.dimmed())),
+                }
+            }
+        }
+        if let Some(child) = &frame.node.extension_child {
+            out.push_str(&format!("{}\n", "Child:".bold()));
+            match child {
+                NodeRef::Hash(h) => {
+                    out.push_str(&format!("  {} hash 0x{}\n", "[>]".cyan(), hex::encode(h)))
+                }
+                NodeRef::Inline(d) => {
+                    out.push_str(&format!("  {} inline {} bytes\n", "[>]".cyan(), d.len()))
+                }
+            }
+        }
+        out
+    }
+}
+
+pub fn db_browse_app(matches: ArgMatches) -> Result<(), Error> {
+    let db_path = matches.value_of("path").expect("db path is required");
+    let simple_trie = SimpleTrie {
+        db: setup_db_connection(db_path)?,
+    };
+    let state_root_hash = get_state_root_hash(&matches, "root hash", "block", &simple_trie.db)?;
+
+    info!("SSI Version: {}", env!("CARGO_PKG_VERSION"));
+    info!("DB path: {}", db_path);
+    info!("State root hash: {:?}", state_root_hash);
+
+    let mut browser = TrieBrowser::new(&simple_trie, state_root_hash)?;
+    if matches.is_present("storage key") || matches.is_present("pallet") {
+        let storage_key_hash = get_storage_key_hash(&matches)?;
+        info!("Storage key hash: {}", storage_key_hash);
+        let path: Vec<u8> = storage_key_hash
+            .chars()
+            .map(|c| map_char_to_pos(c) as u8)
+            .collect();
+        browser.walk(&path)?;
+    }
+
+    let stdin = io::stdin();
+    let mut lines = stdin.lock().lines();
+    let mut message = String::new();
+    loop {
+        print!("\x1B[2J\x1B[H{}", browser.render());
+        if !message.is_empty() {
+            println!("{}", message.red());
+        }
+        print!("[0-f] step into, [>] extension child, [..] step out, [r] root, [q] quit > ");
+        io::stdout().flush()?;
+        message.clear();
+
+        let line = match lines.next() {
+            Some(l) => l?,
+            None => return Ok(()),
+        };
+        match line.trim() {
+            "q" | "quit" => return Ok(()),
+            ".." | "u" => {
+                if !browser.step_out() {
+                    message = "already at the root node".to_string();
+                }
+            }
+            "r" => browser.back_to_root(),
+            ">" => {
+                if let Err(e) = browser.step_into_extension() {
+                    message = format!("{}", e);
+                }
+            }
+            "" => (),
+            cmd if cmd.len() == 1 && cmd.chars().all(|c| c.is_ascii_hexdigit()) => {
+                let slot = cmd.chars().next().map(map_char_to_pos).unwrap_or_default();
+                if let Err(e) = browser.step_into(slot) {
+                    message = format!("{}", e);
+                }
+            }
+            cmd => message = format!("unknown command {}", cmd),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::{Hash, Layout};
+    use hash_db::Prefix;
+    use trie_db::{ChildReference, NodeCodec, TrieLayout};
+
+    type Codec = <Layout<Hasher> as TrieLayout>::Codec;
+
+    /// The DB only has the root node, the children are inlined in the root
+    struct RootOnly(Vec<u8>);
+
+    impl HashDBRef<Hasher, Vec<u8>> for RootOnly {
+        fn get(&self, _key: &Hash, _prefix: Prefix) -> Option<Vec<u8>> {
+            Some(self.0.clone())
+        }
+
+        fn contains(&self, _key: &Hash, _prefix: Prefix) -> bool {
+            true
+        }
+    }
+
+    fn inline_leaf(partial: &[u8], value: &[u8]) -> Option<ChildReference<Hash>> {
+        let data = Codec::leaf_node(((0, 0), partial), value);
+        let mut inline: Hash = Default::default();
+        inline[..data.len()].copy_from_slice(&data);
+        Some(ChildReference::Inline(inline, data.len()))
+    }
+
+    #[test]
+    fn test_trie_browser() {
+        let mut children = vec![None; 16];
+        children[1] = inline_leaf(&[0x23], b"one");
+        children[10] = inline_leaf(&[0xbc], b"ten");
+        let root = Codec::branch_node_nibbled(vec![0x3a].into_iter(), 2, children.iter(), None);
+        let db = RootOnly(root);
+
+        let mut browser = TrieBrowser::new(&db, Default::default()).unwrap();
+        assert_eq!(browser.node_kind(), NodeKind::NibbledBranch);
+        assert_eq!(browser.node_path(), vec![3, 10]);
+        assert!(browser.render().contains("[a] inline"));
+        assert!(browser.step_into(2).is_err());
+
+        browser.step_into(10).unwrap();
+        assert_eq!(browser.node_kind(), NodeKind::Leaf);
+        assert_eq!(nibbles_to_hex(&browser.node_path()), "3aabc");
+        assert!(browser.render().contains("0x74656e"));
+        assert!(browser.step_out());
+        assert!(!browser.step_out());
+
+        browser.walk(&[3, 10, 1, 2, 3]).unwrap();
+        assert_eq!(nibbles_to_hex(&browser.node_path()), "3a123");
+        browser.back_to_root();
+        assert_eq!(browser.node_path(), vec![3, 10]);
+    }
+}
@@ -0,0 +1,82 @@
+/// Inspect the default child tries
+///
+/// The root of a default child trie is stored in the main trie with the key of
+/// `:child_storage:default:` ++ child id, and the nodes of the child trie are stored in DB with
+/// the child id as the keyspace before the prefix of node key.
+use hash_db::HashDBRef;
+
+use crate::codec::well_known::DEFAULT_CHILD_STORAGE_KEY_PREFIX;
+use crate::errors::Error;
+use crate::inspector::db::Data;
+use crate::inspector::subtrie::SubtrieIter;
+use crate::storage::{setup_db_connection, Hasher, KeyspacedTrie, SimpleTrie};
+
+/// Get the child id and the child trie root if the node at the path is the root of a default
+/// child trie
+pub fn child_trie_root(path: &str, value: &[u8]) -> Option<(Vec<u8>, [u8; 32])> {
+    let key = hex::decode(path).ok()?;
+    let child_id = key.strip_prefix(DEFAULT_CHILD_STORAGE_KEY_PREFIX)?;
+    if value.len() != 32 || child_id.is_empty() {
+        return None;
+    }
+    let mut root: [u8; 32] = Default::default();
+    root.copy_from_slice(value);
+    Some((child_id.to_vec(), root))
+}
+
+/// List the nodes of the child trie in the opened DB, the paths are in the child trie
+pub fn read_child_trie_node(
+    db: &dyn HashDBRef<Hasher, Vec<u8>>,
+    child_id: &[u8],
+    child_root_hash: [u8; 32],
+    leaf_only: bool,
+) -> Result<Vec<(String, Data)>, Error> {
+    let child_trie = KeyspacedTrie::new(db, child_id);
+    SubtrieIter::new(&child_trie, "", child_root_hash, true, leaf_only).collect()
+}
+
+/// List the nodes of the child trie, the paths are in the child trie
+pub fn get_child_trie_node(
+    child_id: &[u8],
+    db_path: &str,
+    child_root_hash: [u8; 32],
+    leaf_only: bool,
+) -> Result<Vec<(String, Data)>, Error> {
+    let simple_trie = SimpleTrie {
+        db: setup_db_connection(db_path)?,
+    };
+    read_child_trie_node(&simple_trie, child_id, child_root_hash, leaf_only)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::inspector::record::NodeRecord;
+    use crate::storage::tests::{insert_trie, PrefixRecorder};
+    use crate::storage::MemoryTrie;
+
+    #[test]
+    fn test_child_trie() {
+        let key = hex::encode(b":child_storage:default:contract");
+        let (child_id, root) = child_trie_root(&key, &[7; 32]).unwrap();
+        assert_eq!(child_id, b"contract".to_vec());
+        assert_eq!(root, [7; 32]);
+        assert!(child_trie_root(&key, &[7; 8]).is_none());
+        assert!(child_trie_root(&hex::encode(b":code"), &[7; 32]).is_none());
+
+        let mut db = MemoryTrie::default();
+        let root = insert_trie(&mut db, &[(b"ssi", &[1; 40]), (b"zz", &[2; 40])]);
+        let recorder = PrefixRecorder::new(&db);
+        let child_trie = KeyspacedTrie::new(&recorder, &child_id);
+        let nodes = SubtrieIter::new(&child_trie, "", root, true, true)
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(nodes.len(), 2);
+        let prefixes = recorder.prefixes.borrow();
+        assert!(prefixes.iter().all(|p| p.starts_with(b"contract")));
+
+        let (path, data) = nodes[0].clone();
+        let record = NodeRecord::new_child(&child_id, path, data);
+        assert_eq!(record.semantic.to_string(), "child contract > 0x737369");
+    }
+}
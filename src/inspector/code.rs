@@ -0,0 +1,81 @@
+/// Extract the runtime wasm blob at a state root
+///
+/// The runtime code is the value of the well known `:code` key, which is looked up through the
+/// trie from the state root, so the code active at any block kept in DB can be extracted.
+use std::fs;
+
+use hash_db::HashDBRef;
+use serde_json::Value;
+
+use crate::cli::ArgMatches;
+use crate::codec::wasm::code_summary;
+use crate::codec::well_known::CODE;
+use crate::errors::Error;
+use crate::inspector::db::get_state_root_hash;
+use crate::inspector::proof::key_to_nibbles;
+use crate::logger::info;
+use crate::storage::{lookup_value, setup_db_connection, Hasher, SimpleTrie};
+
+/// Look up the runtime code in the state, `None` is returned if `:code` is not in the state
+pub fn read_runtime_code(
+    db: &dyn HashDBRef<Hasher, Vec<u8>>,
+    state_root_hash: [u8; 32],
+) -> Result<Option<Vec<u8>>, Error> {
+    lookup_value(db, state_root_hash, &key_to_nibbles(&hex::encode(CODE))?)
+}
+
+fn code_not_found(state_root_hash: [u8; 32]) -> Error {
+    Error::DBDataIncorrect(
+        format!("state root 0x{}", hex::encode(state_root_hash)),
+        ":code is not in the state".to_string(),
+    )
+}
+
+/// Get the runtime code at the state root in DB
+pub fn get_runtime_code(db_path: &str, state_root_hash: [u8; 32]) -> Result<Vec<u8>, Error> {
+    let simple_trie = SimpleTrie {
+        db: setup_db_connection(db_path)?,
+    };
+    read_runtime_code(&simple_trie, state_root_hash)?.ok_or_else(|| code_not_found(state_root_hash))
+}
+
+pub fn db_extract_code_app(matches: ArgMatches) -> Result<(), Error> {
+    let db_path = matches.value_of("path").expect("db path is required");
+    let output = matches.value_of("output").expect("output file is required");
+    let simple_trie = SimpleTrie {
+        db: setup_db_connection(db_path)?,
+    };
+    let state_root_hash = get_state_root_hash(&matches, "root hash", "block", &simple_trie.db)?;
+
+    info!("SSI Version: {}", env!("CARGO_PKG_VERSION"));
+    info!("DB path: {}", db_path);
+    info!("State root hash: {:?}", state_root_hash);
+
+    let code = read_runtime_code(&simple_trie, state_root_hash)?
+        .ok_or_else(|| code_not_found(state_root_hash))?;
+    fs::write(output, &code)?;
+    info!("{} bytes of code are written to {}", code.len(), output);
+
+    let mut summary = code_summary(&code);
+    summary["root"] = Value::String(format!("0x{}", hex::encode(state_root_hash)));
+    summary["output"] = Value::String(output.to_string());
+    println!("{}", summary);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::codec::wasm::tests::{sample_code, sample_version};
+    use crate::storage::tests::memory_trie;
+
+    #[test]
+    fn test_read_runtime_code() {
+        let code = sample_code("runtime_version", &sample_version());
+        let (trie, root) = memory_trie(&[(b":heappages", &[8, 0, 0, 0, 0, 0, 0, 0])]);
+        assert_eq!(read_runtime_code(&trie, root).unwrap(), None);
+        let (trie, root) =
+            memory_trie(&[(b":heappages", &[8, 0, 0, 0, 0, 0, 0, 0]), (CODE, &code)]);
+        assert_eq!(read_runtime_code(&trie, root).unwrap(), Some(code));
+    }
+}
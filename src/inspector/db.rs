@@ -1,29 +1,28 @@
 /// Inspect the TrieNodes in the DB
 ///
-/// Trace the the storage key in the Trie, and list the exactly trie node or the subtrie nodes
-use std::collections::HashMap;
-use std::ops::Range;
+/// Trace the the storage key in the Trie, and list the exactly trie node or the subtrie nodes,
+/// the nodes of the default child tries found in the subtrie are listed after their roots
+use std::io;
 
-use sp_core::hashing::blake2_256;
-use trie_db::{
-    node::{NodeHandlePlan, NodePlan},
-    TrieDB, TrieDBNodeIterator,
-};
+use rocksdb::DB;
+use serde::Serialize;
 
 use crate::cli::ArgMatches;
-use crate::codec::storage_key_semantic_decode;
 use crate::errors::Error;
-use crate::inspector::get_storage_key_hash;
-use crate::logger::{debug, error, info, trace, warn};
-use crate::storage::{
-    map_char_to_pos, map_pos_to_char, raw_query, setup_db_connection, Hasher, Layout, SimpleTrie,
-};
+use crate::inspector::child::child_trie_root;
+use crate::inspector::dot::write_dot;
+use crate::inspector::format::{OutputFormat, RecordWriter};
+use crate::inspector::record::{DiffRecord, NodeRecord};
+use crate::inspector::subtrie::{SubtrieDiffIter, SubtrieIter};
+use crate::inspector::{get_diff_output_format, get_output_format, get_storage_key_hash};
+use crate::logger::{debug, info};
+use crate::storage::{get_block_header, setup_db_connection, BlockId, KeyspacedTrie, SimpleTrie};
 
 /// the (byte data, is leaf node)
-type Data = (Vec<u8>, bool);
+pub type Data = (Vec<u8>, bool);
 
-#[derive(Debug)]
-enum NodeChangeStatus {
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+pub enum NodeChangeStatus {
     Insert,
     // There is may not realy deletion in KVDB, but the node is not recorded in Tire structure,
     // user can not query the data throught Trie, and the node will be deemed as Deleted
@@ -40,504 +39,31 @@ enum NodeChangeStatus {
 /// the byte with 0 means the data not change,
 /// and the byte with positive value means the data is inserted
 /// and the byte with negative value means the data is deleted
-type DiffData = (Vec<i16>, NodeChangeStatus);
+pub type DiffData = (Vec<i16>, NodeChangeStatus);
 
-fn parse_child_hash(c: NodeHandlePlan, data: &[u8]) -> Vec<u8> {
-    match c {
-        NodeHandlePlan::Hash(r) | NodeHandlePlan::Inline(r) => data[r].to_vec(),
-    }
-}
-
-fn parse_value(r: Option<Range<usize>>, data: &[u8]) -> Vec<u8> {
-    if let Some(r) = r {
-        data[r].to_vec()
-    } else {
-        vec![]
-    }
-}
-
-fn pretty_print(prefix: &str, map: HashMap<Vec<u8>, Vec<usize>>) -> String {
-    let mut out = String::from("[\n");
-    for (k, v) in map.iter() {
-        out.push_str(&format!(
-            "\t[{}..{}]({:?}): \t{}\n",
-            k[0],
-            k.last().unwrap_or(&0),
-            k.len(),
-            v.iter().fold(format!("0x{}", prefix), |mut acc, x| {
-                acc.push(map_pos_to_char(*x));
-                acc
-            })
-        ));
-    }
-    out.push(']');
-    out
-}
-
-/// Print the output as JSON format
-fn json_output(output: Vec<(String, Data)>, summary: bool, prefix: &str) -> String {
-    let mut out = String::from("[");
-    if !output.is_empty() {
-        let output_last_idx = output.len() - 1;
-        for (idx, (k, v)) in output.iter().enumerate() {
-            if summary {
-                let hash = if v.0.len() == 32 {
-                    hex::encode(&v.0)
-                } else {
-                    hex::encode(blake2_256(&v.0))
-                };
-                let semantic_result = storage_key_semantic_decode(k, false);
-                out.push_str(&format!(
-                    r#"{{"0x{}":{{"hash":"0x{}","length":{},"leaf":{},"subtrie_path":"{}","pallet":"{}","field":"{}","key":"{}"}}}}"#,
-                    k,
-                    hash,
-                    v.0.len(),
-                    v.1,
-                    k.strip_prefix(prefix).unwrap(),
-                    semantic_result.0.unwrap_or_default(),
-                    semantic_result.1.unwrap_or_default(),
-                    semantic_result.2.unwrap_or_default(),
-                ));
-            } else {
-                out.push_str(&format!(r#"{{"{}":{:?}}}"#, k, v.0));
-            }
-            if idx < output_last_idx {
-                out.push(',');
-            }
-        }
-    }
-    out.push(']');
-    out
-}
-
-/// Print the difference as JSON format
-fn json_diff(output: Vec<(String, DiffData)>, summary: bool, prefix: &str) -> String {
-    let mut out = String::from("[");
-    if !output.is_empty() {
-        let output_last_idx = output.len() - 1;
-        for (idx, (k, v)) in output.iter().enumerate() {
-            if summary {
-                let semantic_result = storage_key_semantic_decode(k, false);
-                out.push_str(&format!(
-                    r#"{{"0x{}":{{"length":{}, "change_length":{},"status":"{:?}","subtrie_path":"{}","pallet":"{}","field":"{}","key":"{}"}}}}"#,
-                    k,
-                    v.0.len(),
-                    v.0.iter().fold(0, |acc, x| if *x !=0 {acc + 1} else {acc}),
-                    v.1,
-                    k.strip_prefix(prefix).unwrap(),
-                    semantic_result.0.unwrap_or_default(),
-                    semantic_result.1.unwrap_or_default(),
-                    semantic_result.2.unwrap_or_default(),
-                ));
-            } else {
-                out.push_str(&format!(r#"{{"{}":{:?}}}"#, k, v.0));
-            }
-            if idx < output_last_idx {
-                out.push(',');
-            }
-        }
-    }
-    out.push(']');
-    out
-}
-
-fn get_subtrie_node(
+/// Get the exactly trie node or the subtrie nodes of the storage key hash in the state,
+/// the nodes are listed with the trie path in hex
+pub fn get_subtrie_node(
     storage_key_hash: &str,
     db_path: &str,
     state_root_hash: [u8; 32],
     including_children: bool,
     leaf_only: bool,
 ) -> Result<Vec<(String, Data)>, Error> {
-    let mut output: Vec<(String, Data)> = Vec::new();
-
-    let storage_key: Vec<usize> = storage_key_hash.chars().map(map_char_to_pos).collect();
-    debug!("Storage Key Path: {:?}", storage_key);
-
-    let (db, cfs) = setup_db_connection(db_path);
-    let (db2, _) = setup_db_connection(db_path);
     let simple_trie = SimpleTrie {
-        db,
-        cfs: cfs.clone(),
+        db: setup_db_connection(db_path)?,
     };
-
-    // TODO: handle unwarp here
-    let trie = TrieDB::<Layout<Hasher>>::new(&simple_trie, &state_root_hash).unwrap();
-    let mut node_iter = TrieDBNodeIterator::new(&trie).unwrap();
-    let mut path_iter = storage_key.iter();
-
-    let mut target_node_key = Some(state_root_hash.to_vec());
-    // child tree, hash -> path
-    let mut children_hash_to_path: HashMap<Vec<u8>, Vec<usize>> = HashMap::new();
-
-    let mut node_count = 0;
-
-    loop {
-        let current_node = node_iter.next();
-        trace!("current node: {:?}", current_node);
-        if current_node.is_none() {
-            break;
-        }
-
-        // TODO handle the 2nd unwrap
-        let n = current_node.unwrap().unwrap();
-
-        if n.1.is_none() {
-            // some node not inspect
-            let k = n.0.inner();
-            trace!("Ignored Key({}): {:?}", k.len(), k);
-            continue;
-        }
-        let node_key = n.1.unwrap();
-        let node_plan = n.2.node_plan();
-        trace!("Key({}): {:?}", node_key.len(), node_key);
-        if node_key == *target_node_key.clone().unwrap() {
-            info!("Find the {} nodes", node_count);
-            debug!("node key: {:?}", target_node_key);
-            let path = path_iter.next();
-
-            // TODO refactor this
-            let data = raw_query(&db2, &cfs, n.0.as_prefix(), node_key);
-            trace!("prefix: {:?}, node key {:?}", n.0.as_prefix(), node_key);
-            let data =
-                data.expect("node key error, open trace log for futher for finding the root cause");
-            debug!("data for {:?}: {} length bytes", node_key, data.len());
-
-            if let Some(mut p) = path {
-                match node_plan {
-                    NodePlan::Leaf { value, .. } => {
-                        warn!("run into leaf before the path drain");
-                        let value = parse_value(Some(value.clone()), &data);
-                        if value.len() == 32 {
-                            target_node_key = Some(value);
-                            continue;
-                        } else {
-                            error!("Run into leaf node early");
-                            output.push((storage_key_hash.to_string(), (value, true)));
-                            break;
-                        }
-                    }
-                    NodePlan::NibbledBranch {
-                        children,
-                        value,
-                        partial,
-                    } => {
-                        let partial_nibble = partial.build(&data);
-                        debug!("partial: {:?}", partial_nibble);
-                        debug!("children: {:?}", children);
-                        debug!(
-                            "Get a NibbledBranch ({}), partial path {:?}",
-                            node_count, partial_nibble
-                        );
-                        for (idx, n) in partial_nibble.iter().enumerate() {
-                            if idx == 0 {
-                                if *p != n as usize {
-                                    error!(
-                                        "trie path not match path: {}({}), nibble: {}",
-                                        map_pos_to_char(*p),
-                                        p,
-                                        n
-                                    );
-                                }
-                                continue;
-                            }
-                            if let Some(pp) = path_iter.next() {
-                                if *pp != n as usize {
-                                    error!(
-                                        "trie path not match path: {}({}), nibble: {}",
-                                        map_pos_to_char(*p),
-                                        pp,
-                                        n
-                                    );
-                                }
-                                trace!(
-                                    "Find path to \"{}\"({})\t(partial: {:?})",
-                                    map_pos_to_char(*pp),
-                                    pp,
-                                    n
-                                );
-                            }
-                        }
-
-                        let child = if !partial_nibble.is_empty() {
-                            if let Some(pp) = path_iter.next() {
-                                p = pp;
-                                debug!("update path to {}", p);
-                                Some(
-                                    children
-                                        .get(*p)
-                                        .unwrap()
-                                        .clone()
-                                        .expect("trie hash this child")
-                                        .clone(),
-                                )
-                            } else {
-                                None
-                            }
-                        } else {
-                            children.get(*p).expect("trie hash this child").clone()
-                        };
-
-                        if child.is_none() {
-                            debug!("go to the end of path {:?}", partial_nibble);
-                        } else {
-                            debug!(
-                                "skip path to {:?}, and will go to {}",
-                                partial_nibble,
-                                map_pos_to_char(*p)
-                            );
-                        }
-
-                        trace!("value: {:?}", value);
-                        if let Some(c) = child {
-                            debug!("children: {:?}", children);
-                            debug!("child: {:?}", c);
-                            let h = parse_child_hash(c, &data);
-                            target_node_key = Some(h);
-                            debug!("new target node key: {:?}", target_node_key);
-                        } else if including_children {
-                            for (idx, child) in children.iter().enumerate() {
-                                if let Some(c) = child {
-                                    children_hash_to_path
-                                        .insert(parse_child_hash(c.clone(), &data), vec![idx]);
-                                }
-                            }
-                        } else {
-                            output.push((
-                                storage_key_hash.to_string(),
-                                (parse_value(value.clone(), &data), true),
-                            ));
-                            break;
-                        }
-                    }
-                    NodePlan::Branch { children, value } => {
-                        debug!(
-                            "Get a Branch ({}), find path to \"{}\"({})",
-                            node_count,
-                            map_pos_to_char(*p),
-                            p
-                        );
-                        trace!("value: {:?}", value);
-
-                        if let Some(c) = children.get(*p).expect("trie hash this child").clone() {
-                            trace!("children: {:?}", children);
-                            debug!("child: {:?}", c);
-                            let h = parse_child_hash(c, &data);
-                            target_node_key = Some(h);
-                            debug!("new target node key: {:?}", target_node_key);
-                        } else {
-                            error!("children: {:?}", children);
-                            error!("Path end at {}, Branch node has no child for here", p);
-                            break;
-                        }
-                    }
-                    NodePlan::Extension { child, partial } => {
-                        let partial_nibble = partial.build(&data);
-                        trace!("partial: {:?}", partial_nibble);
-
-                        let mut from_node_check = false;
-                        for n in partial_nibble.iter() {
-                            if !from_node_check {
-                                if *p != n as usize {
-                                    error!(
-                                        "trie path not match path: {}({}), nibble: {}",
-                                        map_pos_to_char(*p),
-                                        p,
-                                        n
-                                    );
-                                }
-                                from_node_check = true;
-                                continue;
-                            }
-                            if let Some(p) = path_iter.next() {
-                                debug!(
-                                    "Find path to \"{}\"({})\t(partial: {:?})",
-                                    map_pos_to_char(*p),
-                                    p,
-                                    n
-                                );
-                            }
-                        }
-
-                        if let Some(p) = path_iter.next() {
-                            debug!(
-                                "Get an Extension ({}), skip path to {:?}, and will go to {}",
-                                node_count,
-                                partial_nibble,
-                                map_pos_to_char(*p)
-                            );
-                        } else {
-                            debug!(
-                                "Get an Extension ({}), go to the end of path {:?}",
-                                node_count, partial_nibble
-                            );
-                        }
-
-                        debug!("child: {:?}", child);
-                        let h = parse_child_hash(child.clone(), &data);
-                        target_node_key = Some(h);
-                        debug!("new target node key: {:?}", target_node_key);
-                    }
-                    _ => {
-                        error!("Nonexistent, please check your input parameters");
-                        return Ok(output);
-                    }
-                };
-            } else {
-                // This end of path
-                match node_plan {
-                    NodePlan::Leaf { value, .. } => {
-                        let value = parse_value(Some(value.clone()), &data);
-                        if value.len() == 32 {
-                            info!("Get the last node, and it is child trie");
-                            children_hash_to_path.insert(value, vec![]);
-                        } else {
-                            info!("Get th last node, and it is Leaf");
-                            output.push((storage_key_hash.to_string(), (value, true)));
-                            break;
-                        }
-                    }
-                    NodePlan::Branch { children, .. } => {
-                        info!("Get the last node, and it is Branch");
-                        if !leaf_only && !including_children {
-                            output.push((storage_key_hash.to_string(), (vec![], false)));
-                        }
-                        if including_children {
-                            for (idx, child) in children.iter().enumerate() {
-                                if let Some(c) = child {
-                                    children_hash_to_path
-                                        .insert(parse_child_hash(c.clone(), &data), vec![idx]);
-                                }
-                            }
-                        } else {
-                            error!("Get the last node but it is branch");
-                            break;
-                        }
-                    }
-                    NodePlan::NibbledBranch { children, .. } => {
-                        if including_children {
-                            for (idx, child) in children.iter().enumerate() {
-                                if let Some(c) = child {
-                                    children_hash_to_path
-                                        .insert(parse_child_hash(c.clone(), &data), vec![idx]);
-                                }
-                            }
-                        } else {
-                            error!("Get the last node but it is branch");
-                            break;
-                        }
-                    }
-                    NodePlan::Extension { partial, child } => {
-                        info!("Get the last node, and it is extension");
-                        if !leaf_only {
-                            output.push((storage_key_hash.to_string(), (vec![], false)));
-                        }
-                        if including_children {
-                            let partial_nibble = partial.build(&data);
-                            let mut partial_path = vec![];
-                            let mut from_node_check = false;
-                            for n in partial_nibble.iter() {
-                                if !from_node_check {
-                                    from_node_check = true;
-                                    continue;
-                                }
-                                partial_path.push(n as usize);
-                            }
-
-                            children_hash_to_path
-                                .insert(parse_child_hash(child.clone(), &data), partial_path);
-                        } else {
-                            error!("Get the last node but it is extension");
-                            output.push((storage_key_hash.to_string(), (vec![], false)));
-                            break;
-                        }
-                    }
-                    NodePlan::Empty => {
-                        warn!("Get the last node but it is empty");
-                        output.push((storage_key_hash.to_string(), (vec![], false)));
-                    }
-                };
-            }
-            node_count += 1;
-        } else if including_children && children_hash_to_path.contains_key(&node_key.to_vec()) {
-            // TODO refactor this
-            let data = raw_query(&db2, &cfs, n.0.as_prefix(), node_key);
-            debug!("prefix: {:?}, node key {:?}", n.0.as_prefix(), node_key);
-            let data =
-                data.expect("node key error, open trace log for futher for finding the root cause");
-            debug!("data for {:?}: {} length bytes", node_key, data.len());
-            let path_prefix = children_hash_to_path
-                .get(&node_key.to_vec())
-                .unwrap()
-                .clone();
-            let trie_key = path_prefix
-                .iter()
-                .fold(storage_key_hash.to_string(), |mut acc, x| {
-                    acc.push(map_pos_to_char(*x));
-                    acc
-                });
-
-            match node_plan {
-                NodePlan::Leaf { value, .. } => {
-                    info!("Find 0x{} in 0x{} subtrie", trie_key, storage_key_hash);
-                    output.push((trie_key, (parse_value(Some(value.clone()), &data), true)));
-                }
-                NodePlan::Branch { children, .. } => {
-                    if including_children {
-                        for (idx, child) in children.iter().enumerate() {
-                            if let Some(c) = child {
-                                let mut path = path_prefix.clone();
-                                path.push(idx);
-                                children_hash_to_path
-                                    .insert(parse_child_hash(c.clone(), &data), path);
-                            }
-                        }
-                    }
-                }
-                NodePlan::NibbledBranch {
-                    value,
-                    children,
-                    partial,
-                    ..
-                } => {
-                    if !leaf_only {
-                        output.push((trie_key, (parse_value(value.clone(), &data), false)));
-                    }
-                    if including_children {
-                        let partial_nibble = partial.build(&data);
-                        let mut partial_path = partial_nibble
-                            .iter()
-                            .map(|b| b as usize)
-                            .collect::<Vec<usize>>();
-                        for (idx, child) in children.iter().enumerate() {
-                            if let Some(c) = child {
-                                let mut path = path_prefix.clone();
-                                path.append(&mut partial_path);
-                                path.push(idx);
-                                children_hash_to_path
-                                    .insert(parse_child_hash(c.clone(), &data), path);
-                            }
-                        }
-                    }
-                }
-                NodePlan::Extension { partial, child } => {
-                    let mut path = path_prefix;
-                    path.append(&mut vec![16; partial.len()]);
-                    children_hash_to_path.insert(parse_child_hash(child.clone(), &data), path);
-                }
-                NodePlan::Empty => {
-                    warn!("Last node is empty");
-                }
-            };
-        }
-    }
-    trace!(
-        "overall nodes in substrie: {}",
-        pretty_print(storage_key_hash, children_hash_to_path)
-    );
-    Ok(output)
+    SubtrieIter::new(
+        &simple_trie,
+        storage_key_hash,
+        state_root_hash,
+        including_children,
+        leaf_only,
+    )
+    .collect()
 }
 
-fn hex_str_to_state_hash(
+pub(super) fn hex_str_to_state_hash(
     state_root_hash: &mut [u8; 32],
     raw_state_root_hash: &str,
 ) -> Result<(), Error> {
@@ -560,18 +86,102 @@ fn hex_str_to_state_hash(
     Ok(())
 }
 
+/// Read the state root hash from the header of the block in the opened DB, None if the block is
+/// not in DB
+pub fn read_block_state_root(db: &DB, block_id: &BlockId) -> Result<Option<[u8; 32]>, Error> {
+    Ok(get_block_header(db, block_id)?.map(|header| {
+        debug!(
+            "Block #{} parent hash: {:?}, extrinsics root hash: {:?}",
+            header.number, header.parent_hash, header.extrinsics_root
+        );
+        info!(
+            "Block #{} state root hash: {:?}",
+            header.number, header.state_root
+        );
+        header.state_root
+    }))
+}
+
+/// Get the state root hash from the header of the block, None if the block is not in DB
+pub fn get_block_state_root(db_path: &str, block_id: &BlockId) -> Result<Option<[u8; 32]>, Error> {
+    read_block_state_root(&setup_db_connection(db_path)?, block_id)
+}
+
+/// Get the state root hash from the root hash option,
+/// or from the header of the block given by the block option
+pub(super) fn get_state_root_hash(
+    matches: &ArgMatches,
+    root_hash_option: &str,
+    block_option: &str,
+    db: &DB,
+) -> Result<[u8; 32], Error> {
+    let mut state_root_hash: [u8; 32] = Default::default();
+    if let Some(raw_state_root_hash) = matches.value_of(root_hash_option) {
+        hex_str_to_state_hash(&mut state_root_hash, raw_state_root_hash)?;
+    } else if let Some(raw_block) = matches.value_of(block_option) {
+        let block_id = if raw_block.starts_with("0x") {
+            let mut block_hash: [u8; 32] = Default::default();
+            hex_str_to_state_hash(&mut block_hash, raw_block)?;
+            BlockId::Hash(block_hash)
+        } else {
+            BlockId::Number(raw_block.parse::<u32>().map_err(|e| {
+                Error::OptionValueIncorrect(block_option.to_string(), format!("{}", e))
+            })?)
+        };
+        state_root_hash = read_block_state_root(db, &block_id)?
+            .ok_or_else(|| Error::BlockNotFound(raw_block.to_string()))?;
+    } else {
+        return Err(Error::OptionValueIncorrect(
+            root_hash_option.to_string(),
+            format!("{} or {} is required", root_hash_option, block_option),
+        ));
+    }
+    Ok(state_root_hash)
+}
+
+/// Compare the subtrie nodes of the storage key hash between two states
+pub fn get_subtrie_diff(
+    storage_key_hash: &str,
+    db_path: &str,
+    state_root_hash_1: [u8; 32],
+    state_root_hash_2: [u8; 32],
+    including_children: bool,
+    leaf_only: bool,
+) -> Result<Vec<(String, DiffData)>, Error> {
+    let simple_trie = SimpleTrie {
+        db: setup_db_connection(db_path)?,
+    };
+    SubtrieDiffIter::new(
+        SubtrieIter::new(
+            &simple_trie,
+            storage_key_hash,
+            state_root_hash_1,
+            including_children,
+            leaf_only,
+        ),
+        SubtrieIter::new(
+            &simple_trie,
+            storage_key_hash,
+            state_root_hash_2,
+            including_children,
+            leaf_only,
+        ),
+    )
+    .collect()
+}
+
 pub fn db_inspect_app(matches: ArgMatches) -> Result<(), Error> {
     let storage_key_hash = &get_storage_key_hash(&matches)?;
     let summary = matches.is_present("summarize output");
+    let format = get_output_format(&matches)?;
     let including_children = !matches.is_present("exactly");
     let leaf_only = !matches.is_present("all node");
-    let raw_state_root_hash = matches
-        .value_of("root hash")
-        .expect("root hash is required");
     let db_path = matches.value_of("path").expect("db path is required");
+    let simple_trie = SimpleTrie {
+        db: setup_db_connection(db_path)?,
+    };
 
-    let mut state_root_hash: [u8; 32] = Default::default();
-    hex_str_to_state_hash(&mut state_root_hash, raw_state_root_hash)?;
+    let state_root_hash = get_state_root_hash(&matches, "root hash", "block", &simple_trie.db)?;
 
     info!("SSI Version: {}", env!("CARGO_PKG_VERSION"));
     info!("DB path: {}", db_path);
@@ -580,32 +190,60 @@ pub fn db_inspect_app(matches: ArgMatches) -> Result<(), Error> {
     info!("State root hash: {:?}", state_root_hash);
     info!("Storage key hash: {}", storage_key_hash);
     info!("Sumarize data: {}", summary);
-
-    let output = get_subtrie_node(
+    info!("Output format: {:?}", format);
+
+    let stdout = io::stdout();
+    if format == OutputFormat::Dot {
+        return write_dot(
+            stdout.lock(),
+            &simple_trie,
+            storage_key_hash,
+            state_root_hash,
+            including_children,
+        );
+    }
+    let mut writer = RecordWriter::new(stdout.lock(), format, summary)?;
+    for node in SubtrieIter::new(
+        &simple_trie,
         storage_key_hash,
-        db_path,
         state_root_hash,
         including_children,
         leaf_only,
-    )?;
-    println!("{}", json_output(output, summary, storage_key_hash));
+    ) {
+        let (k, v) = node?;
+        let child = if including_children {
+            child_trie_root(&k, &v.0)
+        } else {
+            None
+        };
+        writer.write(&NodeRecord::new(k, v, storage_key_hash))?;
+        if let Some((child_id, child_root_hash)) = child {
+            debug!("Child trie 0x{}", hex::encode(&child_id));
+            let child_trie = KeyspacedTrie::new(&simple_trie, &child_id);
+            for node in SubtrieIter::new(&child_trie, "", child_root_hash, true, leaf_only) {
+                let (k, v) = node?;
+                writer.write(&NodeRecord::new_child(&child_id, k, v))?;
+            }
+        }
+    }
+    writer.finish()?;
     Ok(())
 }
 pub fn db_diff_app(matches: ArgMatches) -> Result<(), Error> {
     let storage_key_hash = &get_storage_key_hash(&matches)?;
     let summary = matches.is_present("summarize output");
+    let format = get_diff_output_format(&matches)?;
     let including_children = !matches.is_present("exactly");
     let leaf_only = !matches.is_present("all node");
-    let raw_state_root_hash = matches
-        .value_of("root hash")
-        .expect("root hash is required");
     let db_path = matches.value_of("path").expect("db path is required");
+    let simple_trie = SimpleTrie {
+        db: setup_db_connection(db_path)?,
+    };
 
-    let mut state_root_hash_1: [u8; 32] = Default::default();
-    hex_str_to_state_hash(&mut state_root_hash_1, raw_state_root_hash)?;
+    let state_root_hash_1 = get_state_root_hash(&matches, "root hash", "block", &simple_trie.db)?;
 
-    let mut state_root_hash_2: [u8; 32] = Default::default();
-    hex_str_to_state_hash(&mut state_root_hash_2, raw_state_root_hash)?;
+    let state_root_hash_2 =
+        get_state_root_hash(&matches, "root hash diff", "block diff", &simple_trie.db)?;
 
     info!("SSI Version: {}", env!("CARGO_PKG_VERSION"));
     info!("DB path: {}", db_path);
@@ -615,72 +253,91 @@ pub fn db_diff_app(matches: ArgMatches) -> Result<(), Error> {
     info!("State root hash diff: {:?}", state_root_hash_2);
     info!("Storage key hash: {}", storage_key_hash);
     info!("Sumarize data: {}", summary);
-
-    let origin: HashMap<_, _> = get_subtrie_node(
-        storage_key_hash,
-        db_path,
-        state_root_hash_1,
-        including_children,
-        leaf_only,
-    )?
-    .into_iter()
-    .collect();
-
-    let after: HashMap<_, _> = get_subtrie_node(
-        storage_key_hash,
-        db_path,
-        state_root_hash_2,
-        including_children,
-        leaf_only,
-    )?
-    .into_iter()
-    .collect();
-
-    let mut output: Vec<(String, DiffData)> = Vec::new();
-
-    for (k, v) in after.iter() {
-        // Modify is only the same structure, so we assume it is same length
-        if origin.contains_key(k) {
-            let origin_value = origin.get(k).unwrap();
-            if origin_value.0.len() == v.0.len() {
-                let mut node_change_status = NodeChangeStatus::Unchanged;
-                let diff = origin_value
-                    .0
-                    .iter()
-                    .zip(v.0.iter())
-                    .map(|(b, a)| {
-                        if a == b {
-                            0
-                        } else {
-                            node_change_status = NodeChangeStatus::Modify;
-                            *a as i16
-                        }
-                    })
-                    .collect::<Vec<i16>>();
-                output.push((k.clone(), (diff, node_change_status)));
-                continue;
-            }
-        }
-        output.push((
-            k.clone(),
-            (
-                v.0.iter().map(|b| *b as i16).collect(),
-                NodeChangeStatus::Insert,
-            ),
-        ))
+    info!("Output format: {:?}", format);
+
+    let stdout = io::stdout();
+    let mut writer = RecordWriter::new(stdout.lock(), format, summary)?;
+    for diff in SubtrieDiffIter::new(
+        SubtrieIter::new(
+            &simple_trie,
+            storage_key_hash,
+            state_root_hash_1,
+            including_children,
+            leaf_only,
+        ),
+        SubtrieIter::new(
+            &simple_trie,
+            storage_key_hash,
+            state_root_hash_2,
+            including_children,
+            leaf_only,
+        ),
+    ) {
+        let (k, v) = diff?;
+        writer.write(&DiffRecord::new(k, v, storage_key_hash))?;
     }
+    writer.finish()?;
+    Ok(())
+}
 
-    for (k, v) in origin.iter() {
-        if !after.contains_key(k) {
-            output.push((
-                k.clone(),
-                (
-                    v.0.iter().map(|b| -(*b as i16)).collect(),
-                    NodeChangeStatus::Delete,
-                ),
-            ))
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sp_core::hashing::blake2_128;
+
+    /// The accounts transfer balances from block #5 to block #50 in the sample DB,
+    /// (Alice, Bob, Charlie, Dave, Eve, Ferdie)
+    const TRANSFER_ACCOUNTS: [&str; 6] = [
+        "d43593c715fdd31c61141abd04a99fd6822c8558854ccde39a5684e7a56da27d",
+        "8eaf04151687736326c9fea17e25fc5287613693c912909cb226aa4794f26a48",
+        "90b5ab205c6974c9ea841be688864633dc9ca8a357843eeacf2314649965fe22",
+        "306721211d5404bd9da88e0204360a1a9ab8b87c66c1bc2fcdd37f3c2222cc20",
+        "e659a7a1628cdd93febc04a4e0646ea20e9f5f0ce097d9a05290d4a9e054df4e",
+        "1cbd2d43530a44705ad088af313e18f80b53ef16b36177cd4b77b846f2a5f07c",
+    ];
+
+    #[test]
+    fn test_diff_balance_transfer() {
+        let db_path = concat!(env!("CARGO_MANIFEST_DIR"), "/db");
+        let storage_key_hash = "26aa394eea5630e07c48ae0c9558cef7b99d880ec681799c0cf30e8886371da9";
+        let mut block_5_root: [u8; 32] = Default::default();
+        hex_str_to_state_hash(
+            &mut block_5_root,
+            "0x940a55c41ce61b2d771e82f8a6c6f4939a712a644502f5efa7c59afea0a3a67e",
+        )
+        .unwrap();
+        let mut block_50_root: [u8; 32] = Default::default();
+        hex_str_to_state_hash(
+            &mut block_50_root,
+            "0x3b559d574c4a9f13e55d0256655f0f71a70a703766226f1080f80022e39c057d",
+        )
+        .unwrap();
+
+        let output = get_subtrie_diff(
+            storage_key_hash,
+            db_path,
+            block_5_root,
+            block_50_root,
+            true,
+            true,
+        )
+        .unwrap();
+
+        for account in TRANSFER_ACCOUNTS.iter() {
+            let account_key = format!(
+                "{}{}{}",
+                storage_key_hash,
+                hex::encode(blake2_128(&hex::decode(account).unwrap())),
+                account
+            );
+            assert!(
+                output
+                    .iter()
+                    .any(|(k, (_, status))| account_key.starts_with(k)
+                        && matches!(status, NodeChangeStatus::Modify)),
+                "account {} is not modified",
+                account
+            );
         }
     }
-    println!("{}", json_diff(output, summary, storage_key_hash));
-    Ok(())
 }
@@ -0,0 +1,127 @@
+/// Draw the subtrie of storage key as a Graphviz DOT graph
+///
+/// The nodes are labelled with the node kind, the partial nibbles, the value length and the
+/// semantic key, and the edges are labelled with the nibble of child slot.  The children stored
+/// by hash are drawn with solid edges and the inline children with dashed edges.
+use std::io::Write;
+
+use hash_db::HashDBRef;
+
+use crate::errors::Error;
+use crate::inspector::record::SemanticKey;
+use crate::inspector::subtrie::{SubtrieIter, SubtrieNode};
+use crate::storage::{map_pos_to_char, nibbles_to_hex, Hasher, NodeRef};
+
+/// The drawn node with the node id, the nibble path and whether it is an extension node
+type Drawn = (String, Vec<u8>, bool);
+
+/// Escape the quote and backslash in the DOT string
+fn dot_escape(s: &str) -> String {
+    s.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+/// Write the exactly trie node or the subtrie nodes of the storage key hash as a DOT graph,
+/// the nodes are written as they are found
+pub fn write_dot<W: Write>(
+    mut out: W,
+    db: &dyn HashDBRef<Hasher, Vec<u8>>,
+    storage_key_hash: &str,
+    state_root_hash: [u8; 32],
+    including_children: bool,
+) -> Result<(), Error> {
+    writeln!(out, "digraph subtrie {{")?;
+    writeln!(out, "  node [shape=box, fontname=monospace];")?;
+
+    // the drawn nodes from the top of subtrie to the parent of current node
+    let mut ancestors: Vec<Drawn> = Vec::new();
+    for n in SubtrieIter::new(
+        db,
+        storage_key_hash,
+        state_root_hash,
+        including_children,
+        false,
+    )
+    .nodes()
+    {
+        let SubtrieNode {
+            path,
+            node_ref,
+            node,
+        } = n?;
+        let id = format!("0x{}", nibbles_to_hex(&path));
+        let semantic = SemanticKey::decode(&nibbles_to_hex(&path));
+        let mut label = format!(
+            "{:?}\\npartial: {}\\nvalue: {}",
+            node.kind,
+            nibbles_to_hex(&node.partial),
+            node.value
+                .as_ref()
+                .map(|v| format!("{} bytes", v.len()))
+                .unwrap_or_else(|| "-".to_string())
+        );
+        if !semantic.pallet.is_empty() {
+            label.push_str(&format!("\\n{}", dot_escape(&semantic.to_string())));
+        }
+        let style = match node_ref {
+            NodeRef::Hash(_) => "",
+            NodeRef::Inline(_) => ", style=rounded",
+        };
+        writeln!(out, "  \"{}\" [label=\"{}\"{}];", id, label, style)?;
+
+        // the nodes are in the order of trie path, so the parent is the last drawn node on the
+        // path to current node
+        while let Some((_, p, _)) = ancestors.last() {
+            if path.starts_with(p) {
+                break;
+            }
+            ancestors.pop();
+        }
+        if let Some((parent_id, parent_path, extension)) = ancestors.last() {
+            let slot = if *extension {
+                String::new()
+            } else {
+                map_pos_to_char(path[parent_path.len()] as usize).to_string()
+            };
+            let style = match node_ref {
+                NodeRef::Hash(_) => "solid",
+                NodeRef::Inline(_) => "dashed",
+            };
+            writeln!(
+                out,
+                "  \"{}\" -> \"{}\" [label=\"{}\", style={}];",
+                parent_id, id, slot, style
+            )?;
+        }
+        ancestors.push((id, path, node.extension_child.is_some()));
+    }
+    writeln!(out, "}}")?;
+    out.flush()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::tests::sample_trie;
+
+    #[test]
+    fn test_write_dot() {
+        let db = sample_trie();
+        let mut out = Vec::new();
+        write_dot(&mut out, &db, "3a", Default::default(), true).unwrap();
+        let dot = String::from_utf8(out).unwrap();
+        assert!(dot.starts_with("digraph subtrie {\n"));
+        assert!(dot.contains("  \"0x3a\" [label=\"NibbledBranch\\npartial: 3a\\nvalue: -\"];\n"));
+        assert!(dot.contains(
+            "  \"0x3a123\" [label=\"Leaf\\npartial: 23\\nvalue: 3 bytes\", style=rounded];\n"
+        ));
+        assert!(dot.contains("  \"0x3a\" -> \"0x3a123\" [label=\"1\", style=dashed];\n"));
+        assert!(dot.contains("  \"0x3a\" -> \"0x3aabc\" [label=\"a\", style=solid];\n"));
+
+        let mut out = Vec::new();
+        write_dot(&mut out, &db, "3aabc", Default::default(), false).unwrap();
+        let dot = String::from_utf8(out).unwrap();
+        assert!(dot.contains("\"0x3aabc\""));
+        assert!(!dot.contains("->"));
+    }
+}
@@ -0,0 +1,191 @@
+/// Export the storage of a state as the raw genesis storage of chain spec
+///
+/// All the values under the state root are exported as `{"0x<key>": "0x<value>"}`, the same as
+/// the `genesis.raw.top` section of a raw chain spec, and the key value pairs of the default
+/// child tries are exported under `childrenDefault` by the child id instead of the child roots,
+/// so the exported state can be used to fork a chain into a local dev chain.
+/// The values are written as they are read in the order of trie path, which is the sorted order
+/// of keys, so the whole state is never kept in memory.
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+
+use hash_db::HashDBRef;
+
+use crate::cli::ArgMatches;
+use crate::errors::Error;
+use crate::inspector::child::child_trie_root;
+use crate::inspector::db::get_state_root_hash;
+use crate::inspector::get_storage_key_hash;
+use crate::inspector::subtrie::SubtrieIter;
+use crate::logger::{debug, info};
+use crate::storage::{nibbles_to_hex, setup_db_connection, Hasher, KeyspacedTrie, SimpleTrie};
+
+/// Write the key value pairs as a JSON object with the keys and values in hex, the pairs are
+/// written as they are read, and the number of pairs is returned
+fn write_object<W: Write>(
+    out: &mut W,
+    pairs: impl Iterator<Item = Result<(Vec<u8>, Vec<u8>), Error>>,
+    indent: &str,
+) -> Result<usize, Error> {
+    write!(out, "{{")?;
+    let mut count = 0;
+    for pair in pairs {
+        let (key, value) = pair?;
+        if count > 0 {
+            write!(out, ",")?;
+        }
+        write!(
+            out,
+            "\n{}  \"0x{}\": \"0x{}\"",
+            indent,
+            nibbles_to_hex(&key),
+            hex::encode(value)
+        )?;
+        count += 1;
+    }
+    if count > 0 {
+        write!(out, "\n{}", indent)?;
+    }
+    write!(out, "}}")?;
+    Ok(count)
+}
+
+/// Write the values under the storage key hash prefix in the format of `genesis.raw` of chain
+/// spec, and the values of the default child tries whose roots are under the prefix, the numbers
+/// of the exported keys and child tries are returned
+pub fn write_raw_storage<W: Write>(
+    mut out: W,
+    db: &dyn HashDBRef<Hasher, Vec<u8>>,
+    storage_key_hash: &str,
+    state_root_hash: [u8; 32],
+) -> Result<(usize, usize), Error> {
+    // the child trie roots are exported after the top trie
+    let mut children = Vec::new();
+    let top = SubtrieIter::new(db, storage_key_hash, state_root_hash, true, false)
+        .nodes()
+        .values()
+        .filter(|pair| match pair {
+            Ok((key, value)) => match child_trie_root(&nibbles_to_hex(key), value) {
+                Some(child) => {
+                    children.push(child);
+                    false
+                }
+                None => true,
+            },
+            Err(_) => true,
+        });
+    write!(out, "{{\n  \"top\": ")?;
+    let keys = write_object(&mut out, top, "  ")?;
+
+    write!(out, ",\n  \"childrenDefault\": {{")?;
+    for (idx, (child_id, child_root_hash)) in children.iter().enumerate() {
+        debug!("Child trie 0x{}", hex::encode(child_id));
+        let child_trie = KeyspacedTrie::new(db, child_id);
+        let child_values = SubtrieIter::new(&child_trie, "", *child_root_hash, true, false)
+            .nodes()
+            .values();
+        if idx > 0 {
+            write!(out, ",")?;
+        }
+        write!(out, "\n    \"0x{}\": ", hex::encode(child_id))?;
+        write_object(&mut out, child_values, "    ")?;
+    }
+    if !children.is_empty() {
+        write!(out, "\n  ")?;
+    }
+    writeln!(out, "}}\n}}")?;
+    out.flush()?;
+    Ok((keys, children.len()))
+}
+
+pub fn db_export_app(matches: ArgMatches) -> Result<(), Error> {
+    let storage_key_hash = if matches.is_present("storage key") || matches.is_present("pallet") {
+        get_storage_key_hash(&matches)?
+    } else {
+        String::new()
+    };
+    let db_path = matches.value_of("path").expect("db path is required");
+    let simple_trie = SimpleTrie {
+        db: setup_db_connection(db_path)?,
+    };
+    let state_root_hash = get_state_root_hash(&matches, "root hash", "block", &simple_trie.db)?;
+
+    info!("SSI Version: {}", env!("CARGO_PKG_VERSION"));
+    info!("DB path: {}", db_path);
+    info!("State root hash: {:?}", state_root_hash);
+    info!("Storage key hash: {}", storage_key_hash);
+
+    let (keys, children) = if let Some(output) = matches.value_of("output") {
+        let file = BufWriter::new(File::create(output)?);
+        write_raw_storage(file, &simple_trie, &storage_key_hash, state_root_hash)?
+    } else {
+        let stdout = io::stdout();
+        write_raw_storage(
+            stdout.lock(),
+            &simple_trie,
+            &storage_key_hash,
+            state_root_hash,
+        )?
+    };
+    info!("{} keys and {} child tries are exported", keys, children);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::codec::well_known::DEFAULT_CHILD_STORAGE_KEY_PREFIX;
+    use crate::storage::tests::{insert_trie, memory_trie};
+    use serde_json::{json, Value};
+
+    #[test]
+    fn test_write_raw_storage() {
+        let (mut trie, child_root) = memory_trie(&[(b"balance", &[7; 40])]);
+        let mut child_key = DEFAULT_CHILD_STORAGE_KEY_PREFIX.to_vec();
+        child_key.extend_from_slice(b"contract");
+        let root = insert_trie(
+            &mut trie,
+            &[
+                (b"ssi", b"one"),
+                (b"ssi:two", &[2; 40]),
+                (b":code", &[0; 4]),
+                (&child_key, child_root.as_ref()),
+            ],
+        );
+        let hex = |data: &[u8]| format!("0x{}", hex::encode(data));
+
+        let mut out = Vec::new();
+        assert_eq!(
+            write_raw_storage(&mut out, &trie, "", root).unwrap(),
+            (3, 1)
+        );
+        let json: Value = serde_json::from_slice(&out).unwrap();
+        assert_eq!(
+            json,
+            json!({
+                "top": {
+                    hex(b":code"): hex(&[0; 4]),
+                    hex(b"ssi"): hex(b"one"),
+                    hex(b"ssi:two"): hex(&[2; 40]),
+                },
+                "childrenDefault": {
+                    hex(b"contract"): {
+                        hex(b"balance"): hex(&[7; 40]),
+                    },
+                },
+            })
+        );
+        // the keys are written in the sorted order
+        let out = String::from_utf8(out).unwrap();
+        assert!(out.find(&hex(b":code")) < out.find(&hex(b"ssi")));
+
+        let mut out = Vec::new();
+        assert_eq!(
+            write_raw_storage(&mut out, &trie, &hex::encode(b"ssi"), root).unwrap(),
+            (2, 0)
+        );
+        let json: Value = serde_json::from_slice(&out).unwrap();
+        assert_eq!(json["top"].as_object().unwrap().len(), 2);
+        assert_eq!(json["childrenDefault"], json!({}));
+    }
+}
@@ -0,0 +1,463 @@
+/// The output formats of the records
+///
+/// - `json`: a JSON array of records, the default format
+/// - `jsonl`: one JSON record per line, the records are written as they are found
+/// - `csv`: the fields of records with a header line
+/// - `table`: the aligned `pallet > field > key`, length and value preview for human reading
+/// - `hex`: the hex dump of the data in records
+/// - `dot`: the Graphviz DOT graph of the subtrie, which is drawn from the trie nodes instead of
+///   the records, see `write_dot`
+use std::io::{self, Write};
+use std::marker::PhantomData;
+use std::str::FromStr;
+
+use serde_json::Value;
+
+use crate::errors::Error;
+use crate::inspector::record::{DiffRecord, KeyDiffRecord, NodeRecord, SemanticKey};
+
+/// The max characters of value preview in table
+const PREVIEW_LEN: usize = 48;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum OutputFormat {
+    Json,
+    JsonLines,
+    Csv,
+    Table,
+    Hex,
+    Dot,
+}
+
+impl FromStr for OutputFormat {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "json" => Ok(OutputFormat::Json),
+            "jsonl" => Ok(OutputFormat::JsonLines),
+            "csv" => Ok(OutputFormat::Csv),
+            "table" => Ok(OutputFormat::Table),
+            "hex" => Ok(OutputFormat::Hex),
+            "dot" => Ok(OutputFormat::Dot),
+            _ => Err(Error::OptionValueIncorrect(
+                "format".to_string(),
+                format!("{} is not one of json, jsonl, csv, table, hex, dot", s),
+            )),
+        }
+    }
+}
+
+/// The record can be written in all the output formats
+pub trait Record {
+    /// The column names of CSV
+    fn columns(summary: bool) -> Vec<&'static str>;
+    /// The fields of CSV
+    fn fields(&self, summary: bool) -> Vec<String>;
+    fn to_json(&self, summary: bool) -> Value;
+    /// The length and value preview shown in table
+    fn describe(&self) -> (String, String);
+    /// The labeled data for hex dump
+    fn dump(&self) -> Vec<(&'static str, Vec<u8>)>;
+    /// The storage key of the record in hex
+    fn path(&self) -> &str;
+    fn semantic(&self) -> &SemanticKey;
+}
+
+/// The name of storage key in `pallet > field > key` format, or the hex if it can not be decoded
+fn key_name<R: Record>(record: &R) -> String {
+    if record.semantic().pallet.is_empty() && record.semantic().child.is_none() {
+        format!("0x{}", record.path())
+    } else {
+        record.semantic().to_string()
+    }
+}
+
+/// Shorten the value for preview
+fn preview(value: &Value) -> String {
+    let s = match value {
+        Value::String(s) => s.clone(),
+        v => v.to_string(),
+    };
+    if s.chars().count() > PREVIEW_LEN {
+        format!("{}...", s.chars().take(PREVIEW_LEN - 3).collect::<String>())
+    } else {
+        s
+    }
+}
+
+fn semantic_columns() -> Vec<&'static str> {
+    vec!["pallet", "field", "key", "key2", "child"]
+}
+
+fn semantic_fields(semantic: &SemanticKey) -> Vec<String> {
+    vec![
+        semantic.pallet.clone(),
+        semantic.field.clone(),
+        semantic.key.clone(),
+        semantic.key2.clone().unwrap_or_default(),
+        semantic.child.clone().unwrap_or_default(),
+    ]
+}
+
+impl Record for NodeRecord {
+    fn columns(summary: bool) -> Vec<&'static str> {
+        let mut out = vec!["path", "subtrie_path", "hash", "length", "leaf"];
+        out.extend(semantic_columns());
+        if !summary {
+            out.push("value");
+        }
+        out
+    }
+
+    fn fields(&self, summary: bool) -> Vec<String> {
+        let mut out = vec![
+            format!("0x{}", self.path),
+            self.subtrie_path.clone(),
+            self.hash.clone(),
+            self.length.to_string(),
+            self.leaf.to_string(),
+        ];
+        out.extend(semantic_fields(&self.semantic));
+        if !summary {
+            out.push(match &self.value {
+                Value::String(s) => s.clone(),
+                v => v.to_string(),
+            });
+        }
+        out
+    }
+
+    fn to_json(&self, summary: bool) -> Value {
+        NodeRecord::to_json(self, summary)
+    }
+
+    fn describe(&self) -> (String, String) {
+        (self.length.to_string(), preview(&self.value))
+    }
+
+    fn dump(&self) -> Vec<(&'static str, Vec<u8>)> {
+        vec![("value", self.data.clone())]
+    }
+
+    fn path(&self) -> &str {
+        &self.path
+    }
+
+    fn semantic(&self) -> &SemanticKey {
+        &self.semantic
+    }
+}
+
+impl Record for DiffRecord {
+    fn columns(summary: bool) -> Vec<&'static str> {
+        let mut out = vec!["path", "subtrie_path", "length", "change_length", "status"];
+        out.extend(semantic_columns());
+        if !summary {
+            out.push("diff");
+        }
+        out
+    }
+
+    fn fields(&self, summary: bool) -> Vec<String> {
+        let mut out = vec![
+            format!("0x{}", self.path),
+            self.subtrie_path.clone(),
+            self.length.to_string(),
+            self.change_length.to_string(),
+            format!("{:?}", self.status),
+        ];
+        out.extend(semantic_fields(&self.semantic));
+        if !summary {
+            out.push(Value::from(self.diff.clone()).to_string());
+        }
+        out
+    }
+
+    fn to_json(&self, summary: bool) -> Value {
+        DiffRecord::to_json(self, summary)
+    }
+
+    fn describe(&self) -> (String, String) {
+        (
+            format!("{}/{}", self.change_length, self.length),
+            format!("{:?}", self.status),
+        )
+    }
+
+    fn dump(&self) -> Vec<(&'static str, Vec<u8>)> {
+        vec![(
+            "diff",
+            self.diff.iter().map(|b| b.unsigned_abs() as u8).collect(),
+        )]
+    }
+
+    fn path(&self) -> &str {
+        &self.path
+    }
+
+    fn semantic(&self) -> &SemanticKey {
+        &self.semantic
+    }
+}
+
+impl Record for KeyDiffRecord {
+    fn columns(summary: bool) -> Vec<&'static str> {
+        let mut out = vec!["path", "origin_length", "after_length", "status"];
+        out.extend(semantic_columns());
+        if !summary {
+            out.extend(vec!["origin", "after"]);
+        }
+        out
+    }
+
+    fn fields(&self, summary: bool) -> Vec<String> {
+        let mut out = vec![
+            format!("0x{}", self.path),
+            self.origin_length.to_string(),
+            self.after_length.to_string(),
+            format!("{:?}", self.status),
+        ];
+        out.extend(semantic_fields(&self.semantic));
+        if !summary {
+            out.push(self.origin.clone());
+            out.push(self.after.clone());
+        }
+        out
+    }
+
+    fn to_json(&self, summary: bool) -> Value {
+        KeyDiffRecord::to_json(self, summary)
+    }
+
+    fn describe(&self) -> (String, String) {
+        (
+            format!("{} -> {}", self.origin_length, self.after_length),
+            format!(
+                "{:?} {}",
+                self.status,
+                preview(&Value::String(self.after.clone()))
+            ),
+        )
+    }
+
+    fn dump(&self) -> Vec<(&'static str, Vec<u8>)> {
+        let bytes = |s: &str| hex::decode(s.trim_start_matches("0x")).unwrap_or_default();
+        vec![
+            ("origin", bytes(&self.origin)),
+            ("after", bytes(&self.after)),
+        ]
+    }
+
+    fn path(&self) -> &str {
+        &self.path
+    }
+
+    fn semantic(&self) -> &SemanticKey {
+        &self.semantic
+    }
+}
+
+/// Quote the CSV field if it has comma, quote or line break
+fn csv_field(field: &str) -> String {
+    if field.contains(&[',', '"', '\n', '\r'][..]) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+fn csv_line(fields: &[String]) -> String {
+    fields
+        .iter()
+        .map(|f| csv_field(f))
+        .collect::<Vec<_>>()
+        .join(",")
+}
+
+/// The hex dump of data, 16 bytes per line with offset and printable characters
+fn hex_dump(data: &[u8]) -> String {
+    let mut out = String::new();
+    for (idx, chunk) in data.chunks(16).enumerate() {
+        let hex = chunk
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect::<Vec<_>>()
+            .join(" ");
+        let ascii = chunk
+            .iter()
+            .map(|b| {
+                if b.is_ascii_graphic() || *b == b' ' {
+                    *b as char
+                } else {
+                    '.'
+                }
+            })
+            .collect::<String>();
+        out.push_str(&format!("  {:08x}  {:<47}  |{}|\n", idx * 16, hex, ascii));
+    }
+    out
+}
+
+/// Write the records in the output format, the records are written as they are found except for
+/// the table, which is aligned after all the records are found
+pub struct RecordWriter<W: Write, R: Record> {
+    out: W,
+    format: OutputFormat,
+    summary: bool,
+    count: usize,
+    rows: Vec<[String; 3]>,
+    record: PhantomData<R>,
+}
+
+impl<W: Write, R: Record> RecordWriter<W, R> {
+    pub fn new(out: W, format: OutputFormat, summary: bool) -> io::Result<Self> {
+        let mut writer = RecordWriter {
+            out,
+            format,
+            summary,
+            count: 0,
+            rows: Vec::new(),
+            record: PhantomData,
+        };
+        match format {
+            OutputFormat::Dot => {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "the records can not be written as DOT graph",
+                ))
+            }
+            OutputFormat::Json => write!(writer.out, "[")?,
+            OutputFormat::Csv => writeln!(
+                writer.out,
+                "{}",
+                csv_line(
+                    &R::columns(summary)
+                        .into_iter()
+                        .map(String::from)
+                        .collect::<Vec<_>>()
+                )
+            )?,
+            _ => (),
+        }
+        Ok(writer)
+    }
+
+    pub fn write(&mut self, record: &R) -> io::Result<()> {
+        match self.format {
+            OutputFormat::Json => {
+                if self.count > 0 {
+                    write!(self.out, ",")?;
+                }
+                write!(self.out, "{}", record.to_json(self.summary))?;
+            }
+            OutputFormat::JsonLines => {
+                writeln!(self.out, "{}", record.to_json(self.summary))?;
+                self.out.flush()?;
+            }
+            OutputFormat::Csv => writeln!(self.out, "{}", csv_line(&record.fields(self.summary)))?,
+            OutputFormat::Table => {
+                let (length, value) = record.describe();
+                self.rows.push([key_name(record), length, value]);
+            }
+            OutputFormat::Hex => {
+                writeln!(self.out, "0x{} ({})", record.path(), key_name(record))?;
+                for (label, data) in record.dump() {
+                    writeln!(self.out, " {} ({} bytes)", label, data.len())?;
+                    write!(self.out, "{}", hex_dump(&data))?;
+                }
+            }
+            OutputFormat::Dot => (),
+        }
+        self.count += 1;
+        Ok(())
+    }
+
+    pub fn finish(mut self) -> io::Result<()> {
+        match self.format {
+            OutputFormat::Json => writeln!(self.out, "]")?,
+            OutputFormat::Table => {
+                let mut widths = [0; 2];
+                for row in self.rows.iter() {
+                    widths[0] = widths[0].max(row[0].chars().count());
+                    widths[1] = widths[1].max(row[1].chars().count());
+                }
+                for row in self.rows.iter() {
+                    writeln!(
+                        self.out,
+                        "{:<w0$}  {:>w1$}  {}",
+                        row[0],
+                        row[1],
+                        row[2],
+                        w0 = widths[0],
+                        w1 = widths[1]
+                    )?;
+                }
+            }
+            _ => (),
+        }
+        self.out.flush()
+    }
+}
+
+/// Write all the records in the output format
+pub fn write_records<W: Write, R: Record>(
+    out: W,
+    format: OutputFormat,
+    summary: bool,
+    records: impl Iterator<Item = R>,
+) -> Result<(), Error> {
+    let mut writer = RecordWriter::new(out, format, summary)?;
+    for record in records {
+        writer.write(&record)?;
+    }
+    writer.finish()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::inspector::db::NodeChangeStatus;
+    #[test]
+    fn test_write_records() {
+        let records = vec![
+            KeyDiffRecord::new(
+                "26aa".to_string(),
+                (Some(vec![1]), Some(vec![2, 3]), NodeChangeStatus::Modify),
+            ),
+            KeyDiffRecord::new(
+                "26aabb".to_string(),
+                (None, Some(b"a,\"b\"".to_vec()), NodeChangeStatus::Insert),
+            ),
+        ];
+        let write = |format| {
+            let mut out = Vec::new();
+            write_records(&mut out, format, false, records.clone().into_iter()).unwrap();
+            String::from_utf8(out).unwrap()
+        };
+        assert_eq!(
+            write(OutputFormat::JsonLines).lines().next().unwrap(),
+            r#"{"0x26aa":{"origin":"0x01","after":"0x0203","status":"Modify"}}"#
+        );
+        assert_eq!(
+            serde_json::from_str::<Value>(&write(OutputFormat::Json))
+                .unwrap()
+                .as_array()
+                .unwrap()
+                .len(),
+            2
+        );
+        let csv = write(OutputFormat::Csv);
+        assert_eq!(
+            csv.lines().next().unwrap(),
+            "path,origin_length,after_length,status,pallet,field,key,key2,child,origin,after"
+        );
+        assert_eq!(csv.lines().count(), 3);
+        assert!(write(OutputFormat::Hex).contains("  00000000  61 2c 22 62 22"));
+        let table = write(OutputFormat::Table);
+        assert_eq!(table.lines().count(), 2);
+        assert!(table.lines().all(|l| l.starts_with("0x26aa")));
+        assert!("xml".parse::<OutputFormat>().is_err());
+    }
+}
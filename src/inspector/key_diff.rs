@@ -0,0 +1,368 @@
+/// Inspect the difference of storage keys and values between two states
+///
+/// Both tries are walked from the root at the same time, and the subtries referred by the same
+/// child hash in both states are skipped, so only the changed part of the trie is read from DB.
+use std::cmp::Ordering;
+use std::io;
+
+use hash_db::HashDBRef;
+
+use crate::cli::ArgMatches;
+use crate::errors::Error;
+use crate::inspector::db::{get_state_root_hash, NodeChangeStatus};
+use crate::inspector::format::RecordWriter;
+use crate::inspector::record::KeyDiffRecord;
+use crate::inspector::subtrie::{match_prefix, SubtrieIter};
+use crate::inspector::{get_diff_output_format, get_storage_key_hash};
+use crate::logger::{debug, info, trace};
+use crate::storage::{
+    load_trie_node, map_char_to_pos, nibbles_to_hex, setup_db_connection, Hasher, NodeRef,
+    SimpleTrie,
+};
+
+/// The (origin value, after value, change status) of a storage key
+pub type KeyDiffData = (Option<Vec<u8>>, Option<Vec<u8>>, NodeChangeStatus);
+
+/// Take the changed storage key in hex and its change as soon as the change is found
+pub type KeyDiffSink<'a> = dyn FnMut(String, KeyDiffData) -> Result<(), Error> + 'a;
+
+/// Iterate the storage keys and values in the subtrie from the node in the order of trie path
+fn subtrie_values<'a>(
+    db: &'a dyn HashDBRef<Hasher, Vec<u8>>,
+    node_ref: Option<&NodeRef>,
+    path: &[u8],
+    prefix: &[u8],
+) -> impl Iterator<Item = Result<(Vec<u8>, Vec<u8>), Error>> + 'a {
+    node_ref
+        .map(|n| {
+            SubtrieIter::from_node(db, n.clone(), path.to_vec(), prefix.to_vec(), true, false)
+                .nodes()
+                .values()
+        })
+        .into_iter()
+        .flatten()
+}
+
+fn push_change(
+    key: &[u8],
+    origin: Option<Vec<u8>>,
+    after: Option<Vec<u8>>,
+    output: &mut KeyDiffSink,
+) -> Result<(), Error> {
+    let status = match (&origin, &after) {
+        (None, Some(_)) => NodeChangeStatus::Insert,
+        (Some(_), None) => NodeChangeStatus::Delete,
+        (Some(o), Some(a)) if o != a => NodeChangeStatus::Modify,
+        _ => return Ok(()),
+    };
+    debug!("{:?} 0x{}", status, nibbles_to_hex(key));
+    output(nibbles_to_hex(key), (origin, after, status))
+}
+
+/// Walk the two subtries at the same path, and pass the changes of storage values to the output
+fn diff_subtrie(
+    db: &dyn HashDBRef<Hasher, Vec<u8>>,
+    origin: Option<&NodeRef>,
+    after: Option<&NodeRef>,
+    path: Vec<u8>,
+    prefix: &[u8],
+    output: &mut KeyDiffSink,
+) -> Result<(), Error> {
+    if origin == after {
+        trace!("skip the same subtrie at 0x{}", nibbles_to_hex(&path));
+        return Ok(());
+    }
+    let origin_node = origin.map(|n| load_trie_node(db, n, &path)).transpose()?;
+    let after_node = after.map(|n| load_trie_node(db, n, &path)).transpose()?;
+
+    match (origin_node, after_node) {
+        (Some(o), Some(a))
+            if o.partial == a.partial
+                && o.extension_child.is_some() == a.extension_child.is_some() =>
+        {
+            let mut node_path = path;
+            node_path.extend_from_slice(&o.partial);
+            if !match_prefix(&node_path, prefix) {
+                return Ok(());
+            }
+            if node_path.len() >= prefix.len() {
+                push_change(&node_path, o.value, a.value, output)?;
+            }
+            if o.extension_child.is_some() {
+                return diff_subtrie(
+                    db,
+                    o.extension_child.as_ref(),
+                    a.extension_child.as_ref(),
+                    node_path,
+                    prefix,
+                    output,
+                );
+            }
+            for idx in 0..16 {
+                let mut child_path = node_path.clone();
+                child_path.push(idx as u8);
+                if !match_prefix(&child_path, prefix) {
+                    continue;
+                }
+                diff_subtrie(
+                    db,
+                    o.children.get(idx).and_then(|c| c.as_ref()),
+                    a.children.get(idx).and_then(|c| c.as_ref()),
+                    child_path,
+                    prefix,
+                    output,
+                )?;
+            }
+        }
+        _ => {
+            // The structure of subtrie is changed, merge the values of the two subtries by path
+            let mut origin_values = subtrie_values(db, origin, &path, prefix).peekable();
+            let mut after_values = subtrie_values(db, after, &path, prefix).peekable();
+            loop {
+                let order = match (origin_values.peek(), after_values.peek()) {
+                    (None, None) => break,
+                    (Some(Err(_)), _) | (Some(Ok(_)), None) => Ordering::Less,
+                    (_, Some(Err(_))) | (None, Some(Ok(_))) => Ordering::Greater,
+                    (Some(Ok(o)), Some(Ok(a))) => o.0.cmp(&a.0),
+                };
+                let (o, a) = match order {
+                    Ordering::Less => (origin_values.next().transpose()?, None),
+                    Ordering::Greater => (None, after_values.next().transpose()?),
+                    Ordering::Equal => (
+                        origin_values.next().transpose()?,
+                        after_values.next().transpose()?,
+                    ),
+                };
+                let key = o.as_ref().or(a.as_ref()).map(|(k, _)| k.clone());
+                push_change(
+                    &key.unwrap_or_default(),
+                    o.map(|(_, v)| v),
+                    a.map(|(_, v)| v),
+                    output,
+                )?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Walk the changed storage keys and values under the storage key prefix between two states, and
+/// pass the changes to the output in the order of trie path as they are found
+pub fn walk_key_diff(
+    db: &dyn HashDBRef<Hasher, Vec<u8>>,
+    storage_key_hash: &str,
+    state_root_hash_1: [u8; 32],
+    state_root_hash_2: [u8; 32],
+    output: &mut KeyDiffSink,
+) -> Result<(), Error> {
+    let prefix: Vec<u8> = storage_key_hash
+        .chars()
+        .map(|c| map_char_to_pos(c) as u8)
+        .collect();
+    diff_subtrie(
+        db,
+        Some(&NodeRef::Hash(state_root_hash_1)),
+        Some(&NodeRef::Hash(state_root_hash_2)),
+        Vec::new(),
+        &prefix,
+        output,
+    )
+}
+
+/// Get the changed storage keys and values under the storage key prefix between two states
+pub fn get_key_diff(
+    storage_key_hash: &str,
+    db_path: &str,
+    state_root_hash_1: [u8; 32],
+    state_root_hash_2: [u8; 32],
+) -> Result<Vec<(String, KeyDiffData)>, Error> {
+    let simple_trie = SimpleTrie {
+        db: setup_db_connection(db_path)?,
+    };
+    let mut output = Vec::new();
+    walk_key_diff(
+        &simple_trie,
+        storage_key_hash,
+        state_root_hash_1,
+        state_root_hash_2,
+        &mut |key, diff| {
+            output.push((key, diff));
+            Ok(())
+        },
+    )?;
+    Ok(output)
+}
+
+pub fn db_key_diff_app(matches: ArgMatches) -> Result<(), Error> {
+    let storage_key_hash = &get_storage_key_hash(&matches)?;
+    let summary = matches.is_present("summarize output");
+    let format = get_diff_output_format(&matches)?;
+    let db_path = matches.value_of("path").expect("db path is required");
+    let simple_trie = SimpleTrie {
+        db: setup_db_connection(db_path)?,
+    };
+
+    let state_root_hash_1 = get_state_root_hash(&matches, "root hash", "block", &simple_trie.db)?;
+    let state_root_hash_2 =
+        get_state_root_hash(&matches, "root hash diff", "block diff", &simple_trie.db)?;
+
+    info!("SSI Version: {}", env!("CARGO_PKG_VERSION"));
+    info!("DB path: {}", db_path);
+    info!("State root hash: {:?}", state_root_hash_1);
+    info!("State root hash diff: {:?}", state_root_hash_2);
+    info!("Storage key hash: {}", storage_key_hash);
+    info!("Sumarize data: {}", summary);
+    info!("Output format: {:?}", format);
+
+    let stdout = io::stdout();
+    let mut writer = RecordWriter::new(stdout.lock(), format, summary)?;
+    walk_key_diff(
+        &simple_trie,
+        storage_key_hash,
+        state_root_hash_1,
+        state_root_hash_2,
+        &mut |key, diff| Ok(writer.write(&KeyDiffRecord::new(key, diff))?),
+    )?;
+    writer.finish()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::tests::{insert_trie, PrefixRecorder};
+    use crate::storage::{Hash, MemoryTrie};
+
+    fn nibbles(key: &[u8]) -> Vec<u8> {
+        key.iter().flat_map(|b| vec![b >> 4, b & 0x0f]).collect()
+    }
+
+    /// The two states in the same memory trie, the values under `aa` are not changed, `bb1` is
+    /// changed, `cc` is removed, `dd` is added, and the branch of `ee` is replaced by a leaf
+    fn two_states() -> (MemoryTrie, Hash, Hash) {
+        let mut trie = MemoryTrie::default();
+        let origin = insert_trie(
+            &mut trie,
+            &[
+                (b"aa1", &[1; 40]),
+                (b"aa2", &[2; 40]),
+                (b"bb1", &[3; 40]),
+                (b"bb2", &[4; 40]),
+                (b"cc", &[5; 40]),
+                (b"ee1", &[7; 40]),
+                (b"ee2", &[8; 40]),
+            ],
+        );
+        let after = insert_trie(
+            &mut trie,
+            &[
+                (b"aa1", &[1; 40]),
+                (b"aa2", &[2; 40]),
+                (b"bb1", &[9; 40]),
+                (b"bb2", &[4; 40]),
+                (b"dd", &[6; 40]),
+                (b"ee1", &[7; 40]),
+            ],
+        );
+        (trie, origin, after)
+    }
+
+    #[test]
+    fn test_push_change() {
+        let mut output = Vec::new();
+        let mut sink = |key, diff| {
+            output.push((key, diff));
+            Ok(())
+        };
+        push_change(&[6, 1], Some(vec![1]), Some(vec![1]), &mut sink).unwrap();
+        push_change(&[6, 1], None, None, &mut sink).unwrap();
+        push_change(&[6, 1], Some(vec![1]), Some(vec![2]), &mut sink).unwrap();
+        push_change(&[6, 2], None, Some(vec![2]), &mut sink).unwrap();
+        push_change(&[6, 3], Some(vec![3]), None, &mut sink).unwrap();
+        // the same values and the missing values are not changes
+        assert_eq!(
+            output,
+            vec![
+                (
+                    "61".to_string(),
+                    (Some(vec![1]), Some(vec![2]), NodeChangeStatus::Modify)
+                ),
+                (
+                    "62".to_string(),
+                    (None, Some(vec![2]), NodeChangeStatus::Insert)
+                ),
+                (
+                    "63".to_string(),
+                    (Some(vec![3]), None, NodeChangeStatus::Delete)
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_subtrie_values() {
+        let (trie, origin, _) = two_states();
+        let output = subtrie_values(&trie, Some(&NodeRef::Hash(origin)), &[], &nibbles(b"b"))
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(
+            output,
+            vec![
+                (nibbles(b"bb1"), vec![3; 40]),
+                (nibbles(b"bb2"), vec![4; 40])
+            ]
+        );
+    }
+
+    #[test]
+    fn test_diff_subtrie() {
+        let (trie, origin, after) = two_states();
+        let recorder = PrefixRecorder::new(&trie);
+        let mut output = Vec::new();
+        diff_subtrie(
+            &recorder,
+            Some(&NodeRef::Hash(origin)),
+            Some(&NodeRef::Hash(after)),
+            Vec::new(),
+            &[],
+            &mut |key, diff| {
+                output.push((key, diff));
+                Ok(())
+            },
+        )
+        .unwrap();
+        let changes: Vec<(String, NodeChangeStatus)> =
+            output.iter().map(|(k, v)| (k.clone(), v.2)).collect();
+        assert_eq!(
+            changes,
+            vec![
+                (hex::encode(b"bb1"), NodeChangeStatus::Modify),
+                (hex::encode(b"cc"), NodeChangeStatus::Delete),
+                (hex::encode(b"dd"), NodeChangeStatus::Insert),
+                (hex::encode(b"ee2"), NodeChangeStatus::Delete),
+            ]
+        );
+        assert_eq!(output[0].1 .0, Some(vec![3; 40]));
+        assert_eq!(output[0].1 .1, Some(vec![9; 40]));
+        // the subtrie of `aa` has the same hash in both states, no node under it is loaded
+        let prefixes = recorder.prefixes.borrow();
+        assert!(!prefixes.is_empty());
+        assert!(prefixes.iter().all(|p| !p.starts_with(b"a")));
+
+        let mut output = Vec::new();
+        diff_subtrie(
+            &trie,
+            Some(&NodeRef::Hash(origin)),
+            Some(&NodeRef::Hash(after)),
+            Vec::new(),
+            &nibbles(b"c"),
+            &mut |key, diff| {
+                output.push((key, diff));
+                Ok(())
+            },
+        )
+        .unwrap();
+        assert_eq!(output.len(), 1);
+        assert_eq!(output[0].0, hex::encode(b"cc"));
+    }
+}
@@ -3,121 +3,310 @@
 /// - stream_inspect_app: read file and inspect the storage key to sematic key
 /// - db_inspect_app: inspect db and subtrie show or sumary the data
 /// - db_diff_app: insepct the two different state of data
+/// - db_key_diff_app: inspect the changed storage keys and values of two different states
+/// - db_browse_app: browse the trie node by node interactively
+/// - db_proof_app: generate the storage proof of storage keys
+/// - verify_proof_app: verify the storage proof and show the proven values
+/// - db_verify_app: verify the integrity of the state trie in DB
+/// - db_extract_code_app: extract the runtime code at a state root to file
+/// - db_export_app: export the state as the raw genesis storage of chain spec
 ///
+/// The apps take the command line options, and the typed functions used by the apps are exported
+/// for the library.
 use sp_core::hashing::twox_128;
 
 use crate::cli::ArgMatches;
 use crate::codec::storage_key_semantic_decode;
-use crate::codec::{blake2_128_concat_encode, twox_64_concat_encode};
+use crate::codec::{hashed_key_encode, storage_key_hashers, typed_key_encode, StorageHasher};
 use crate::errors::Error;
 
+mod browse;
+pub use browse::{db_browse_app, TrieBrowser};
+
+mod child;
+pub use child::{child_trie_root, get_child_trie_node, read_child_trie_node};
+
+mod code;
+pub use code::{db_extract_code_app, get_runtime_code, read_runtime_code};
+
 mod db;
-pub use db::{db_diff_app, db_inspect_app};
+pub use db::{
+    db_diff_app, db_inspect_app, get_block_state_root, get_subtrie_diff, get_subtrie_node,
+    read_block_state_root, Data, DiffData, NodeChangeStatus,
+};
+
+mod export;
+pub use export::{db_export_app, write_raw_storage};
+
+mod key_diff;
+pub use key_diff::{db_key_diff_app, get_key_diff, walk_key_diff, KeyDiffData, KeyDiffSink};
+
+mod dot;
+pub use dot::write_dot;
+
+mod format;
+pub use format::{write_records, OutputFormat, Record, RecordWriter};
+
+mod proof;
+pub use proof::{
+    db_proof_app, get_storage_proof, read_storage_proof, verify_proof_app, verify_storage_proof,
+    ProofRecorder, ProvenValue,
+};
+
+mod record;
+pub use record::{DiffRecord, KeyDiffRecord, NodeRecord, ProofRecord, SemanticKey};
 
 mod stream;
 pub use stream::stream_inspect_app;
 
+mod verify;
+pub use verify::{db_verify_app, verify_trie, IssueKind, TrieIssue, VerifyReport};
+
+mod subtrie;
+pub use subtrie::{SubtrieDiffIter, SubtrieIter};
+
+/// The options of the first key with the hashers, the hasher of `key` option is decided by
+/// `hasher` option or runtime metadata
+const KEY_OPTIONS: [(&str, Option<StorageHasher>); 4] = [
+    ("twox 64 concat", Some(StorageHasher::Twox64Concat)),
+    ("black2 128 concat", Some(StorageHasher::Blake2_128Concat)),
+    ("identity", Some(StorageHasher::Identity)),
+    ("key", None),
+];
+
+/// The options of the 2nd key of double map with the hashers
+const KEY_2ND_OPTIONS: [(&str, Option<StorageHasher>); 4] = [
+    ("twox 64 concat 2nd", Some(StorageHasher::Twox64Concat)),
+    (
+        "black2 128 concat 2nd",
+        Some(StorageHasher::Blake2_128Concat),
+    ),
+    ("identity 2nd", Some(StorageHasher::Identity)),
+    ("key 2nd", None),
+];
+
+/// The key of map or double map, and the hasher used to hash the key
+///
+/// The key is encoded by `typed_key_encode`, and the hasher of the storage entry in runtime metadata
+/// is used if the hasher is not given.
+#[derive(Clone, Copy, Debug)]
+pub struct MapKey<'a> {
+    pub key: &'a str,
+    pub hasher: Option<StorageHasher>,
+}
+
+/// Get the hasher of the key, or the hasher of the storage entry in runtime metadata
+fn get_hasher(
+    option: &str,
+    hasher: Option<StorageHasher>,
+    entry_hasher: Option<StorageHasher>,
+) -> Result<StorageHasher, Error> {
+    hasher.or(entry_hasher).ok_or_else(|| {
+        Error::OptionValueIncorrect(
+            option.to_string(),
+            "hasher is required if the storage entry is not in metadata".to_string(),
+        )
+    })
+}
+
+/// Build the storage key hash in hex from the pallet prefix, storage item name and the keys of
+/// map or double map
+pub fn build_storage_key_hash(
+    pallet: &str,
+    field: Option<&str>,
+    key: Option<MapKey>,
+    key2: Option<MapKey>,
+) -> Result<String, Error> {
+    let mut out = hex::encode(twox_128(pallet.as_bytes()));
+    if let Some(field) = field {
+        out.push_str(&hex::encode(twox_128(field.as_bytes())));
+    } else if key.is_some() {
+        return Err(Error::OptionValueIncorrect(
+            "field".to_string(),
+            "field name is required when genereate a key in that field".to_string(),
+        ));
+    }
+
+    let entry_hashers = storage_key_hashers(&out);
+    if let Some(k) = key {
+        let hasher = get_hasher("hasher", k.hasher, entry_hashers.map(|h| h.0))?;
+        out.push_str(&hashed_key_encode(hasher, &typed_key_encode(k.key)?));
+    } else if key2.is_some() {
+        return Err(Error::OptionValueIncorrect(
+            "twox 64 concat/black2 128 concat/identity/key".to_string(),
+            "one of aformentioned option is required when genereate a secondary key for double map"
+                .to_string(),
+        ));
+    }
+    if let Some(k) = key2 {
+        let hasher = get_hasher("hasher 2nd", k.hasher, entry_hashers.and_then(|h| h.1))?;
+        out.push_str(&hashed_key_encode(hasher, &typed_key_encode(k.key)?));
+    }
+    Ok(out)
+}
+
+/// Get the key from the key options, the hasher of `key` option is given by the hasher option
+fn get_map_key<'a>(
+    matches: &'a ArgMatches,
+    options: &[(&str, Option<StorageHasher>)],
+    hasher_option: &str,
+) -> Result<Option<MapKey<'a>>, Error> {
+    for (option, hasher) in options.iter() {
+        if let Some(key) = matches.value_of(option) {
+            let hasher = match hasher {
+                Some(h) => Some(*h),
+                None => matches
+                    .value_of(hasher_option)
+                    .map(str::parse)
+                    .transpose()?,
+            };
+            return Ok(Some(MapKey { key, hasher }));
+        }
+    }
+    Ok(None)
+}
+
 fn get_storage_key_hash(matches: &ArgMatches) -> Result<String, Error> {
-    if matches.is_present("storage key") {
+    if matches.occurrences_of("storage key") > 1 {
+        // the other keys would be dropped silently in the modes for one storage key
+        Err(Error::OptionValueIncorrect(
+            "storage key".to_string(),
+            "only one storage key is accepted, except for --proof and --verify-proof".to_string(),
+        ))
+    } else if matches.is_present("storage key") {
         // TODO valid date storage key here
         Ok(matches.value_of("storage key").unwrap().to_string())
     } else {
-        let mut out = String::new();
-        let mut first_key = false;
-        out.push_str(&hex::encode(twox_128(
+        build_storage_key_hash(
             matches
                 .value_of("pallet")
-                .expect("pallet is the at last parameter to generate the storage key")
-                .as_bytes(),
-        )));
-        if matches.is_present("field") {
-            out.push_str(&hex::encode(twox_128(
-                matches.value_of("field").unwrap().as_bytes(),
-            )));
-        }
+                .expect("pallet is the at last parameter to generate the storage key"),
+            matches.value_of("field"),
+            get_map_key(matches, &KEY_OPTIONS, "hasher")?,
+            get_map_key(matches, &KEY_2ND_OPTIONS, "hasher 2nd")?,
+        )
+    }
+}
 
-        if matches.is_present("twox 64 concat") {
-            if !matches.is_present("field") {
-                return Err(Error::OptionValueIncorrect(
-                    "field".to_string(),
-                    "field name is required when genereate a key in that field".to_string(),
-                ));
-            }
-            out.push_str(&twox_64_concat_encode(
-                &matches.value_of("twox 64 concat").unwrap(),
-            ));
-            first_key = true;
-        }
-        if matches.is_present("black2 128 concat") {
-            if !matches.is_present("field") {
-                return Err(Error::OptionValueIncorrect(
-                    "field".to_string(),
-                    "field name is required when genereate a key in that field".to_string(),
-                ));
-            }
-            out.push_str(&blake2_128_concat_encode(
-                &matches.value_of("black2 128 concat").unwrap(),
-            ));
-            first_key = true;
-        }
-        if matches.is_present("identity") {
-            if !matches.is_present("field") {
-                return Err(Error::OptionValueIncorrect(
-                    "field".to_string(),
-                    "field name is required when genereate a key in that field".to_string(),
-                ));
-            }
-            out.push_str(&hex::encode(
-                matches.value_of("identity").unwrap().as_bytes(),
-            ));
-            first_key = true;
-        }
-        if matches.is_present("twox 64 concat 2nd") {
-            if !first_key {
-                return Err(Error::OptionValueIncorrect(
-                    "twox 64 concat/black2 128 concat/identity".to_string(),
-                    "one of aformentioned option is required when genereate a secondary key for double map".to_string(),
-                ));
-            }
-            out.push_str(&twox_64_concat_encode(
-                &matches.value_of("twox 64 concat 2nd").unwrap(),
-            ));
-        }
-        if matches.is_present("black2 128 concat 2nd") {
-            if !first_key {
-                return Err(Error::OptionValueIncorrect(
-                    "twox 64 concat/black2 128 concat/identity".to_string(),
-                    "one of aformentioned option is required when genereate a secondary key for double map".to_string(),
-                ));
-            }
-            out.push_str(&blake2_128_concat_encode(
-                &matches.value_of("black2 128 concat 2nd").unwrap(),
-            ));
-        }
-        if matches.is_present("identity 2nd") {
-            if !first_key {
-                return Err(Error::OptionValueIncorrect(
-                    "twox 64 concat/black2 128 concat/identity".to_string(),
-                    "one of aformentioned option is required when genereate a secondary key for double map".to_string(),
-                ));
-            }
-            out.push_str(&hex::encode(
-                matches.value_of("identity 2nd").unwrap().as_bytes(),
-            ));
-        }
-        Ok(out)
+/// Get the output format of the records, JSON by default
+fn get_output_format(matches: &ArgMatches) -> Result<OutputFormat, Error> {
+    matches
+        .value_of("format")
+        .map(str::parse)
+        .unwrap_or(Ok(OutputFormat::Json))
+}
+
+/// The output format of the differences, which can not be drawn as DOT graph
+fn get_diff_output_format(matches: &ArgMatches) -> Result<OutputFormat, Error> {
+    match get_output_format(matches)? {
+        OutputFormat::Dot => Err(Error::OptionValueIncorrect(
+            "format".to_string(),
+            "dot is only for inspecting the subtrie, not for diff".to_string(),
+        )),
+        format => Ok(format),
     }
 }
 
 pub fn decode_storage_key(matches: ArgMatches) -> Result<(), Error> {
-    if let Ok(storage_key_hash) = get_storage_key_hash(&matches) {
-        let semantic_result = storage_key_semantic_decode(&storage_key_hash, true);
-        println!(
-            "{} > {} > {}",
-            semantic_result.0.unwrap_or_default(),
-            semantic_result.1.unwrap_or_default(),
-            semantic_result.2.unwrap_or_default()
+    let storage_key_hash = get_storage_key_hash(&matches)?;
+    let semantic_result = storage_key_semantic_decode(&storage_key_hash, true);
+    println!(
+        "{} > {} > {}{}",
+        semantic_result.0.unwrap_or_default(),
+        semantic_result.1.unwrap_or_default(),
+        semantic_result.2.unwrap_or_default(),
+        semantic_result
+            .3
+            .map(|k| format!(" > {}", k))
+            .unwrap_or_default()
+    );
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use sp_core::hashing::blake2_128;
+    #[test]
+    fn test_build_storage_key_hash() {
+        let alice = "d43593c715fdd31c61141abd04a99fd6822c8558854ccde39a5684e7a56da27d";
+        let key = MapKey {
+            key: "5GrwvaEF5zXb26Fz9rcQpDWS57CtERHpNehXCPcNoHGKutQY",
+            hasher: Some(StorageHasher::Blake2_128Concat),
+        };
+        assert_eq!(
+            build_storage_key_hash("System", Some("Account"), Some(key), None).unwrap(),
+            format!(
+                "26aa394eea5630e07c48ae0c9558cef7b99d880ec681799c0cf30e8886371da9{}{}",
+                hex::encode(blake2_128(&hex::decode(alice).unwrap())),
+                alice
+            )
         );
+        assert!(build_storage_key_hash("System", None, Some(key), None).is_err());
+        assert!(build_storage_key_hash("System", Some("Account"), None, Some(key)).is_err());
+        assert!(build_storage_key_hash(
+            "Ssi",
+            Some("Unknown"),
+            Some(MapKey {
+                key: "u32:1",
+                hasher: None
+            }),
+            None
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_root_alias() {
+        let matches = crate::cli::parse_args(vec![
+            "ssi",
+            "--extract-code",
+            "--root",
+            "0x01",
+            "-o",
+            "runtime.wasm",
+            "./db",
+        ]);
+        assert_eq!(matches.value_of("root hash"), Some("0x01"));
+        assert_eq!(matches.value_of("path"), Some("./db"));
+    }
+
+    #[test]
+    fn test_extract_code_subcommand() {
+        let (subcommand, matches) = crate::cli::subcommand_matches(crate::cli::parse_args(vec![
+            "ssi",
+            "-l",
+            "debug",
+            "extract-code",
+            "--root",
+            "0x01",
+            "./db",
+            "-o",
+            "runtime.wasm",
+        ]));
+        assert_eq!(subcommand.as_deref(), Some("extract-code"));
+        assert_eq!(matches.value_of("root hash"), Some("0x01"));
+        assert_eq!(matches.value_of("path"), Some("./db"));
+        assert_eq!(matches.value_of("output"), Some("runtime.wasm"));
+        assert_eq!(matches.value_of("log"), Some("debug"));
+    }
+
+    #[test]
+    fn test_proof_subcommand() {
+        let (subcommand, matches) = crate::cli::subcommand_matches(crate::cli::parse_args(vec![
+            "ssi", "proof", "-n", "5", "-k", "26aa", "-k", "26ab", "./db",
+        ]));
+        assert_eq!(subcommand.as_deref(), Some("proof"));
+        assert_eq!(matches.value_of("block"), Some("5"));
+        assert_eq!(matches.values_of("storage key").unwrap().count(), 2);
+        assert_eq!(matches.value_of("path"), Some("./db"));
+    }
+
+    #[test]
+    fn test_get_storage_key_hash() {
+        let matches = crate::cli::parse_args(vec!["ssi", "-k", "26aa", "./db"]);
+        assert_eq!(get_storage_key_hash(&matches).unwrap(), "26aa");
+        let matches = crate::cli::parse_args(vec!["ssi", "-k", "26aa", "-k", "26ab", "./db"]);
+        assert!(get_storage_key_hash(&matches).is_err());
     }
-    Ok(())
 }
@@ -0,0 +1,334 @@
+/// Generate and verify the storage proof of storage keys
+///
+/// The trie nodes visited by looking up the storage keys are recorded, and the recorded node set
+/// proves the values of the storage keys in the state, or proves the keys are not in the state.
+/// The proof is verified by looking up the storage keys in the proof nodes kept in memory, which
+/// are keyed by their hashes, so a node not matching the hash in its parent is never found.
+use std::cell::RefCell;
+use std::collections::BTreeMap;
+use std::fs;
+
+use codec::Decode;
+use hash_db::{HashDBRef, Prefix, EMPTY_PREFIX};
+use serde_json::{Map, Value};
+use sp_trie::StorageProof;
+
+use crate::cli::ArgMatches;
+use crate::codec::storage_value_decode;
+use crate::errors::Error;
+use crate::inspector::db::{get_state_root_hash, hex_str_to_state_hash};
+use crate::inspector::get_storage_key_hash;
+use crate::inspector::record::ProofRecord;
+use crate::logger::{debug, info};
+use crate::storage::{lookup_value, setup_db_connection, Hash, Hasher, MemoryTrie, SimpleTrie};
+
+/// the (storage key, the proven value or `None` if the key is proven not in the state)
+pub type ProvenValue = (String, Option<Vec<u8>>);
+
+/// Record the trie nodes read from the DB
+pub struct ProofRecorder<'a> {
+    db: &'a dyn HashDBRef<Hasher, Vec<u8>>,
+    nodes: RefCell<BTreeMap<Hash, Vec<u8>>>,
+}
+
+impl<'a> ProofRecorder<'a> {
+    pub fn new(db: &'a dyn HashDBRef<Hasher, Vec<u8>>) -> Self {
+        ProofRecorder {
+            db,
+            nodes: RefCell::new(BTreeMap::new()),
+        }
+    }
+
+    /// The recorded nodes without duplication
+    pub fn into_proof(self) -> StorageProof {
+        StorageProof::new(self.nodes.into_inner().into_values().collect())
+    }
+}
+
+impl<'a> HashDBRef<Hasher, Vec<u8>> for ProofRecorder<'a> {
+    fn get(&self, key: &Hash, prefix: Prefix) -> Option<Vec<u8>> {
+        let data = self.db.get(key, prefix);
+        if let Some(d) = &data {
+            self.nodes
+                .borrow_mut()
+                .entry(*key)
+                .or_insert_with(|| d.clone());
+        }
+        data
+    }
+
+    fn contains(&self, key: &Hash, prefix: Prefix) -> bool {
+        self.db.contains(key, prefix)
+    }
+}
+
+/// Parse the storage key in hex to the nibbles, the key should be a whole storage key
+pub(super) fn key_to_nibbles(key: &str) -> Result<Vec<u8>, Error> {
+    let key = key.strip_prefix("0x").unwrap_or(key);
+    let bytes = hex::decode(key).map_err(|e| {
+        Error::OptionValueIncorrect("storage key".to_string(), format!("{} {}", key, e))
+    })?;
+    Ok(bytes.iter().flat_map(|b| vec![b >> 4, b & 0x0f]).collect())
+}
+
+/// Read the storage proof of the storage keys in the state from the opened DB
+pub fn read_storage_proof(
+    db: &dyn HashDBRef<Hasher, Vec<u8>>,
+    keys: &[String],
+    state_root_hash: [u8; 32],
+) -> Result<StorageProof, Error> {
+    let recorder = ProofRecorder::new(db);
+    for key in keys.iter() {
+        let value = lookup_value(&recorder, state_root_hash, &key_to_nibbles(key)?)?;
+        debug!(
+            "key {}: {}",
+            key,
+            value.map_or("not in state".to_string(), |v| format!("{} bytes", v.len()))
+        );
+    }
+    Ok(recorder.into_proof())
+}
+
+/// Get the storage proof of the storage keys in the state
+pub fn get_storage_proof(
+    keys: &[String],
+    db_path: &str,
+    state_root_hash: [u8; 32],
+) -> Result<StorageProof, Error> {
+    let simple_trie = SimpleTrie {
+        db: setup_db_connection(db_path)?,
+    };
+    read_storage_proof(&simple_trie, keys, state_root_hash)
+}
+
+pub fn db_proof_app(matches: ArgMatches) -> Result<(), Error> {
+    let keys: Vec<String> = match matches.values_of("storage key") {
+        Some(keys) => keys.map(String::from).collect(),
+        None => vec![get_storage_key_hash(&matches)?],
+    };
+    let db_path = matches.value_of("path").expect("db path is required");
+    let simple_trie = SimpleTrie {
+        db: setup_db_connection(db_path)?,
+    };
+    let state_root_hash = get_state_root_hash(&matches, "root hash", "block", &simple_trie.db)?;
+
+    info!("SSI Version: {}", env!("CARGO_PKG_VERSION"));
+    info!("DB path: {}", db_path);
+    info!("State root hash: {:?}", state_root_hash);
+    info!("Storage keys: {:?}", keys);
+
+    let proof = read_storage_proof(&simple_trie, &keys, state_root_hash)?;
+    println!(
+        "{}",
+        serde_json::to_value(ProofRecord::new(state_root_hash, &keys, proof))?
+    );
+    Ok(())
+}
+
+/// Verify the storage proof of the storage keys in the state, and get the proven values
+pub fn verify_storage_proof(
+    proof: StorageProof,
+    state_root_hash: [u8; 32],
+    keys: &[String],
+) -> Result<Vec<ProvenValue>, Error> {
+    let memory_trie = MemoryTrie::from(proof);
+    if !HashDBRef::contains(&memory_trie, &state_root_hash, EMPTY_PREFIX) {
+        return Err(Error::DBDataIncorrect(
+            format!("state root 0x{}", hex::encode(state_root_hash)),
+            "the root node is not in the proof".to_string(),
+        ));
+    }
+    keys.iter()
+        .map(|key| {
+            let value = lookup_value(&memory_trie, state_root_hash, &key_to_nibbles(key)?)?;
+            Ok((key.clone(), value))
+        })
+        .collect()
+}
+
+/// The proof loaded from file, and the state root and storage keys if they are in the file, the
+/// state root is only for information, the proof is verified with the given state root
+struct ProofFile {
+    proof: StorageProof,
+    root: Option<String>,
+    keys: Vec<String>,
+}
+
+fn proof_data_error(reason: String) -> Error {
+    Error::OptionValueIncorrect("verify proof".to_string(), reason)
+}
+
+fn hex_to_bytes(value: &Value) -> Result<Vec<u8>, Error> {
+    let s = value
+        .as_str()
+        .ok_or_else(|| proof_data_error(format!("{} is not a hex string", value)))?;
+    hex::decode(s.strip_prefix("0x").unwrap_or(s))
+        .map_err(|e| proof_data_error(format!("{} {}", s, e)))
+}
+
+fn nodes_to_proof(nodes: &[Value]) -> Result<StorageProof, Error> {
+    Ok(StorageProof::new(
+        nodes.iter().map(hex_to_bytes).collect::<Result<_, _>>()?,
+    ))
+}
+
+/// Read the proof file, which is the JSON output of `proof`, a JSON array of the encoded trie
+/// nodes in hex, a JSON string of the SCALE encoded `StorageProof` in hex, or the SCALE encoded
+/// `StorageProof` in binary
+fn read_proof_file(path: &str) -> Result<ProofFile, Error> {
+    let content = fs::read(path)?;
+    let decode_proof = |data: &[u8]| {
+        StorageProof::decode(&mut &data[..])
+            .map_err(|e| proof_data_error(format!("StorageProof can not be decoded, {}", e)))
+    };
+    match serde_json::from_slice::<Value>(&content) {
+        Ok(Value::Object(o)) => Ok(ProofFile {
+            proof: nodes_to_proof(
+                o.get("proof")
+                    .and_then(Value::as_array)
+                    .ok_or_else(|| proof_data_error("proof field is missing".to_string()))?,
+            )?,
+            root: o.get("root").and_then(Value::as_str).map(String::from),
+            keys: o
+                .get("keys")
+                .and_then(Value::as_array)
+                .map(|keys| {
+                    keys.iter()
+                        .filter_map(Value::as_str)
+                        .map(String::from)
+                        .collect()
+                })
+                .unwrap_or_default(),
+        }),
+        Ok(Value::Array(nodes)) => Ok(ProofFile {
+            proof: nodes_to_proof(&nodes)?,
+            root: None,
+            keys: Vec::new(),
+        }),
+        Ok(v @ Value::String(_)) => Ok(ProofFile {
+            proof: decode_proof(&hex_to_bytes(&v)?)?,
+            root: None,
+            keys: Vec::new(),
+        }),
+        _ => Ok(ProofFile {
+            proof: decode_proof(&content)?,
+            root: None,
+            keys: Vec::new(),
+        }),
+    }
+}
+
+pub fn verify_proof_app(matches: ArgMatches) -> Result<(), Error> {
+    let proof_path = matches
+        .value_of("verify proof")
+        .expect("proof file is required");
+    let proof_file = read_proof_file(proof_path)?;
+
+    // the root in the proof file comes with the proof, so it can not be trusted to verify the proof
+    let mut state_root_hash: [u8; 32] = Default::default();
+    match matches.value_of("root hash") {
+        Some(root) => hex_str_to_state_hash(&mut state_root_hash, root)?,
+        None => {
+            return Err(Error::OptionValueIncorrect(
+                "root hash".to_string(),
+                format!(
+                    "the trusted root hash is required, the root in the proof file is {}",
+                    proof_file.root.as_deref().unwrap_or("not given")
+                ),
+            ))
+        }
+    }
+    let keys: Vec<String> = if let Some(keys) = matches.values_of("storage key") {
+        keys.map(String::from).collect()
+    } else if matches.is_present("pallet") {
+        vec![get_storage_key_hash(&matches)?]
+    } else if !proof_file.keys.is_empty() {
+        proof_file.keys
+    } else {
+        return Err(Error::OptionValueIncorrect(
+            "storage key".to_string(),
+            "storage keys are required if they are not in the proof file".to_string(),
+        ));
+    };
+
+    info!("SSI Version: {}", env!("CARGO_PKG_VERSION"));
+    info!("Proof file: {}", proof_path);
+    info!("State root hash: {:?}", state_root_hash);
+    if let Some(root) = &proof_file.root {
+        info!("State root hash in the proof file: {}", root);
+    }
+    info!("Storage keys: {:?}", keys);
+
+    let mut out = Map::new();
+    for (key, value) in verify_storage_proof(proof_file.proof, state_root_hash, &keys)? {
+        let key = key.strip_prefix("0x").unwrap_or(&key).to_string();
+        let value = value.map_or(Value::Null, |v| {
+            storage_value_decode(&key, &v)
+                .unwrap_or_else(|| Value::String(format!("0x{}", hex::encode(&v))))
+        });
+        out.insert(format!("0x{}", key), value);
+    }
+    println!("{}", Value::Object(out));
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::tests::{insert_trie, sample_trie};
+
+    #[test]
+    fn test_proof_recorder() {
+        assert_eq!(key_to_nibbles("0x3aAb").unwrap(), vec![3, 10, 10, 11]);
+        assert!(key_to_nibbles("3aa").is_err());
+
+        let db = sample_trie();
+        let recorder = ProofRecorder::new(&db);
+        let root = Default::default();
+        let value = lookup_value(&recorder, root, &[3, 10, 1, 2, 3]).unwrap();
+        assert_eq!(value, Some(b"one".to_vec()));
+        assert_eq!(lookup_value(&recorder, root, &[3, 10, 2]).unwrap(), None);
+        // the inline leaf is in the root node, only the root is recorded
+        assert_eq!(recorder.nodes.borrow().len(), 1);
+
+        let value = lookup_value(&recorder, root, &[3, 10, 10, 11, 12]).unwrap();
+        assert_eq!(value, Some(b"ten".to_vec()));
+        assert_eq!(recorder.into_proof().iter_nodes().count(), 2);
+    }
+
+    #[test]
+    fn test_verify_storage_proof() {
+        let mut db = MemoryTrie::default();
+        let root = insert_trie(
+            &mut db,
+            &[(b"ssi", b"one"), (b"ssi:two", &[2; 40]), (b"zz", &[3; 40])],
+        );
+        let key = hex::encode(b"ssi:two");
+        let recorder = ProofRecorder::new(&db);
+        lookup_value(&recorder, root, &key_to_nibbles(&key).unwrap()).unwrap();
+        let proof = recorder.into_proof();
+
+        let memory_trie = MemoryTrie::from(proof.clone());
+        let value = lookup_value(&memory_trie, root, &key_to_nibbles(&key).unwrap()).unwrap();
+        assert_eq!(value, Some(vec![2; 40]));
+        let keys = vec![key, format!("0x{}", hex::encode(b"ssi:"))];
+        assert_eq!(
+            verify_storage_proof(proof.clone(), root, &keys).unwrap(),
+            vec![
+                (keys[0].clone(), Some(vec![2; 40])),
+                (keys[1].clone(), None)
+            ]
+        );
+        assert!(verify_storage_proof(proof, [9; 32], &keys).is_err());
+
+        // the proof of `zz` does not have the nodes under `ssi`
+        let recorder = ProofRecorder::new(&db);
+        lookup_value(
+            &recorder,
+            root,
+            &key_to_nibbles(&hex::encode(b"zz")).unwrap(),
+        )
+        .unwrap();
+        assert!(verify_storage_proof(recorder.into_proof(), root, &keys).is_err());
+    }
+}
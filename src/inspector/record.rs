@@ -0,0 +1,362 @@
+/// The result records of inspecting
+///
+/// The nodes and the differences found in DB are collected as records with the semantic decoded
+/// storage key, and all the outputs are serialized from the records.
+use std::fmt;
+
+use codec::Encode;
+use serde::Serialize;
+use serde_json::{Map, Value};
+use sp_core::hashing::blake2_256;
+use sp_trie::StorageProof;
+
+use crate::codec::{storage_key_semantic_decode, storage_value_decode};
+use crate::inspector::db::{Data, DiffData, NodeChangeStatus};
+use crate::inspector::key_diff::KeyDiffData;
+
+/// The possible pallet, field and keys of a storage key
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub struct SemanticKey {
+    /// The child id if the key is in a child trie
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub child: Option<String>,
+    pub pallet: String,
+    pub field: String,
+    pub key: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub key2: Option<String>,
+}
+
+impl SemanticKey {
+    pub fn decode(storage_key: &str) -> Self {
+        let (pallet, field, key, key2) = storage_key_semantic_decode(storage_key, false);
+        SemanticKey {
+            child: None,
+            pallet: pallet.unwrap_or_default(),
+            field: field.unwrap_or_default(),
+            key: key.unwrap_or_default(),
+            key2,
+        }
+    }
+
+    /// Decode the key in the child trie, the key is kept in hex if it can not be decoded
+    pub fn decode_child(child_id: &[u8], child_key: &str) -> Self {
+        let mut semantic = SemanticKey::decode(child_key);
+        if semantic.pallet.is_empty() {
+            semantic.key = format!("0x{}", child_key);
+        }
+        semantic.child = Some(match std::str::from_utf8(child_id) {
+            Ok(id) if id.chars().all(|c| c.is_ascii_graphic()) => id.to_string(),
+            _ => format!("0x{}", hex::encode(child_id)),
+        });
+        semantic
+    }
+}
+
+impl fmt::Display for SemanticKey {
+    /// The `pallet > field > key` format, the ` > key2` is followed for double map, and the
+    /// `child <id> > ` is leading for the key in child trie, which is `child <id> > key` if the
+    /// key can not be decoded
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if let Some(child) = &self.child {
+            write!(f, "child {} > ", child)?;
+            if self.pallet.is_empty() && self.field.is_empty() {
+                return write!(f, "{}", self.key);
+            }
+        }
+        write!(f, "{} > {} > {}", self.pallet, self.field, self.key)?;
+        if let Some(key2) = &self.key2 {
+            write!(f, " > {}", key2)?;
+        }
+        Ok(())
+    }
+}
+
+/// The hex string with 0x prefix
+fn hex_value(data: &[u8]) -> String {
+    format!("0x{}", hex::encode(data))
+}
+
+/// A trie node in the subtrie of storage key
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct NodeRecord {
+    /// The trie path of the node in hex
+    pub path: String,
+    /// The path subsequent after the storage key
+    pub subtrie_path: String,
+    /// The data if it is a hash, else the blake2 256 hash of data
+    pub hash: String,
+    pub length: usize,
+    pub leaf: bool,
+    /// The storage value decoded by runtime metadata, or the data in hex
+    pub value: Value,
+    #[serde(flatten)]
+    pub semantic: SemanticKey,
+    /// The data of the node
+    #[serde(skip)]
+    pub data: Vec<u8>,
+}
+
+/// The summary fields of a node
+#[derive(Serialize)]
+struct NodeSummary<'a> {
+    hash: &'a str,
+    length: usize,
+    leaf: bool,
+    subtrie_path: &'a str,
+    #[serde(flatten)]
+    semantic: &'a SemanticKey,
+}
+
+impl NodeRecord {
+    /// The node of the child trie, the `path` is the path in the child trie
+    pub fn new_child(child_id: &[u8], path: String, data: Data) -> Self {
+        let mut record = NodeRecord::new(path, data, "");
+        record.semantic = SemanticKey::decode_child(child_id, &record.path);
+        record
+    }
+
+    pub fn new(path: String, data: Data, prefix: &str) -> Self {
+        let (data, leaf) = data;
+        let hash = if data.len() == 32 {
+            hex_value(&data)
+        } else {
+            hex_value(&blake2_256(&data))
+        };
+        let value = storage_value_decode(&path, &data)
+            .filter(|_| leaf)
+            .unwrap_or_else(|| Value::String(hex_value(&data)));
+        NodeRecord {
+            subtrie_path: path.strip_prefix(prefix).unwrap_or_default().to_string(),
+            semantic: SemanticKey::decode(&path),
+            path,
+            hash,
+            length: data.len(),
+            leaf,
+            value,
+            data,
+        }
+    }
+
+    /// The JSON object of the node, `{"0x{path}": {summary}}` or `{"{path}": value}`
+    pub fn to_json(&self, summary: bool) -> Value {
+        let mut out = Map::new();
+        if summary {
+            out.insert(
+                format!("0x{}", self.path),
+                serde_json::to_value(NodeSummary {
+                    hash: &self.hash,
+                    length: self.length,
+                    leaf: self.leaf,
+                    subtrie_path: &self.subtrie_path,
+                    semantic: &self.semantic,
+                })
+                .unwrap_or_default(),
+            );
+        } else {
+            out.insert(self.path.clone(), self.value.clone());
+        }
+        Value::Object(out)
+    }
+}
+
+/// The difference of a trie node in two states
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct DiffRecord {
+    /// The trie path of the node in hex
+    pub path: String,
+    /// The path subsequent after the storage key
+    pub subtrie_path: String,
+    pub length: usize,
+    /// The number of the changed bytes
+    pub change_length: usize,
+    pub status: NodeChangeStatus,
+    /// The changed bytes, see `DiffData`
+    pub diff: Vec<i16>,
+    #[serde(flatten)]
+    pub semantic: SemanticKey,
+}
+
+/// The summary fields of a node difference
+#[derive(Serialize)]
+struct DiffSummary<'a> {
+    length: usize,
+    change_length: usize,
+    status: NodeChangeStatus,
+    subtrie_path: &'a str,
+    #[serde(flatten)]
+    semantic: &'a SemanticKey,
+}
+
+impl DiffRecord {
+    pub fn new(path: String, diff: DiffData, prefix: &str) -> Self {
+        let (diff, status) = diff;
+        DiffRecord {
+            subtrie_path: path.strip_prefix(prefix).unwrap_or_default().to_string(),
+            semantic: SemanticKey::decode(&path),
+            path,
+            length: diff.len(),
+            change_length: diff.iter().filter(|b| **b != 0).count(),
+            status,
+            diff,
+        }
+    }
+
+    /// The JSON object of the difference, `{"0x{path}": {summary}}` or `{"{path}": diff}`
+    pub fn to_json(&self, summary: bool) -> Value {
+        let mut out = Map::new();
+        if summary {
+            out.insert(
+                format!("0x{}", self.path),
+                serde_json::to_value(DiffSummary {
+                    length: self.length,
+                    change_length: self.change_length,
+                    status: self.status,
+                    subtrie_path: &self.subtrie_path,
+                    semantic: &self.semantic,
+                })
+                .unwrap_or_default(),
+            );
+        } else {
+            out.insert(self.path.clone(), Value::from(self.diff.clone()));
+        }
+        Value::Object(out)
+    }
+}
+
+/// The difference of a storage value in two states
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct KeyDiffRecord {
+    /// The storage key in hex
+    pub path: String,
+    /// The storage value in the origin state, empty if the value is inserted
+    pub origin: String,
+    /// The storage value in the after state, empty if the value is deleted
+    pub after: String,
+    pub origin_length: usize,
+    pub after_length: usize,
+    pub status: NodeChangeStatus,
+    #[serde(flatten)]
+    pub semantic: SemanticKey,
+}
+
+/// The summary fields of a storage value difference
+#[derive(Serialize)]
+struct KeyDiffSummary<'a> {
+    origin_length: usize,
+    after_length: usize,
+    status: NodeChangeStatus,
+    #[serde(flatten)]
+    semantic: &'a SemanticKey,
+}
+
+/// The storage values of a storage value difference
+#[derive(Serialize)]
+struct KeyDiffValues<'a> {
+    origin: &'a str,
+    after: &'a str,
+    status: NodeChangeStatus,
+}
+
+impl KeyDiffRecord {
+    pub fn new(path: String, diff: KeyDiffData) -> Self {
+        let (origin, after, status) = diff;
+        let origin = origin.unwrap_or_default();
+        let after = after.unwrap_or_default();
+        KeyDiffRecord {
+            semantic: SemanticKey::decode(&path),
+            path,
+            origin: hex_value(&origin),
+            after: hex_value(&after),
+            origin_length: origin.len(),
+            after_length: after.len(),
+            status,
+        }
+    }
+
+    /// The JSON object of the difference, `{"0x{path}": {summary or values}}`
+    pub fn to_json(&self, summary: bool) -> Value {
+        let fields = if summary {
+            serde_json::to_value(KeyDiffSummary {
+                origin_length: self.origin_length,
+                after_length: self.after_length,
+                status: self.status,
+                semantic: &self.semantic,
+            })
+        } else {
+            serde_json::to_value(KeyDiffValues {
+                origin: &self.origin,
+                after: &self.after,
+                status: self.status,
+            })
+        };
+        let mut out = Map::new();
+        out.insert(format!("0x{}", self.path), fields.unwrap_or_default());
+        Value::Object(out)
+    }
+}
+
+/// The storage proof of the storage keys in a state
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct ProofRecord {
+    /// The state root hash in hex
+    pub root: String,
+    /// The storage keys in hex
+    pub keys: Vec<String>,
+    /// The encoded trie nodes in hex
+    pub proof: Vec<String>,
+    /// The SCALE encoded `StorageProof` in hex
+    pub storage_proof: String,
+}
+
+impl ProofRecord {
+    pub fn new(root: [u8; 32], keys: &[String], proof: StorageProof) -> Self {
+        ProofRecord {
+            root: hex_value(&root),
+            keys: keys
+                .iter()
+                .map(|k| format!("0x{}", k.strip_prefix("0x").unwrap_or(k)))
+                .collect(),
+            storage_proof: hex_value(&proof.encode()),
+            proof: proof.iter_nodes().map(|n| hex_value(&n)).collect(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn test_record_json_escape() {
+        let record = NodeRecord {
+            path: "3a636f6465".to_string(),
+            subtrie_path: String::new(),
+            hash: "0x00".to_string(),
+            length: 1,
+            leaf: true,
+            value: Value::String("0x00".to_string()),
+            semantic: SemanticKey {
+                pallet: ":code".to_string(),
+                field: "\"quoted\"".to_string(),
+                ..Default::default()
+            },
+            data: vec![0],
+        };
+        assert_eq!(
+            record.to_json(true).to_string(),
+            r#"{"0x3a636f6465":{"hash":"0x00","length":1,"leaf":true,"subtrie_path":"","pallet":":code","field":"\"quoted\"","key":""}}"#
+        );
+        assert_eq!(
+            record.to_json(false).to_string(),
+            r#"{"3a636f6465":"0x00"}"#
+        );
+        let diff = DiffRecord::new(
+            "26aa".to_string(),
+            (vec![0, 3, -1], NodeChangeStatus::Modify),
+            "26",
+        );
+        assert_eq!(diff.change_length, 2);
+        assert_eq!(diff.subtrie_path, "aa");
+        assert_eq!(diff.to_json(false).to_string(), r#"{"26aa":[0,3,-1]}"#);
+    }
+}
@@ -68,10 +68,14 @@ pub fn stream_inspect_app(matches: ArgMatches) -> Result<(), Error> {
                     debug!("capture hex literal: {}", &cap[0]);
                     if semantic_result.0.is_some() {
                         format!(
-                            "{} > {} > {}",
+                            "{} > {} > {}{}",
                             semantic_result.0.unwrap_or_default(),
                             semantic_result.1.unwrap_or_default(),
-                            semantic_result.2.unwrap_or_default()
+                            semantic_result.2.unwrap_or_default(),
+                            semantic_result
+                                .3
+                                .map(|k| format!(" > {}", k))
+                                .unwrap_or_default()
                         )
                     } else {
                         String::new()
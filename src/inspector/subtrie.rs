@@ -0,0 +1,293 @@
+/// Iterate the nodes of subtrie without buffering the whole subtrie
+///
+/// The trie is walked in depth first order with a stack of the nodes to visit, so the nodes are
+/// found in the order of trie path, and only the stack is kept in memory.  The differences of two
+/// subtries are found by merging the two ordered iterators.
+use std::cmp::Ordering;
+use std::iter::Peekable;
+
+use hash_db::HashDBRef;
+
+use crate::errors::Error;
+use crate::inspector::db::{Data, DiffData, NodeChangeStatus};
+use crate::logger::{debug, trace};
+use crate::storage::{load_trie_node, map_char_to_pos, nibbles_to_hex, Hasher, NodeRef, TrieNode};
+
+/// Check the path is in the prefix or the prefix is in the path
+pub(super) fn match_prefix(path: &[u8], prefix: &[u8]) -> bool {
+    path.iter().zip(prefix.iter()).all(|(a, b)| a == b)
+}
+
+/// The trie node in subtrie with the reference to the node, the path of the node is in nibbles and
+/// includes the partial of the node
+pub struct SubtrieNode {
+    pub path: Vec<u8>,
+    pub node_ref: NodeRef,
+    pub node: TrieNode,
+}
+
+/// The iterator of the exactly trie node or the subtrie nodes of the storage key hash,
+/// the nodes are yielded with the trie path in hex and in the order of trie path
+pub struct SubtrieIter<'a> {
+    db: &'a dyn HashDBRef<Hasher, Vec<u8>>,
+    prefix: Vec<u8>,
+    including_children: bool,
+    leaf_only: bool,
+    /// The nodes to visit with the nibble path to the node
+    stack: Vec<(NodeRef, Vec<u8>)>,
+}
+
+impl<'a> SubtrieIter<'a> {
+    pub fn new(
+        db: &'a dyn HashDBRef<Hasher, Vec<u8>>,
+        storage_key_hash: &str,
+        state_root_hash: [u8; 32],
+        including_children: bool,
+        leaf_only: bool,
+    ) -> Self {
+        let prefix: Vec<u8> = storage_key_hash
+            .chars()
+            .map(|c| map_char_to_pos(c) as u8)
+            .collect();
+        debug!("Storage Key Path: {:?}", prefix);
+        Self::from_node(
+            db,
+            NodeRef::Hash(state_root_hash),
+            Vec::new(),
+            prefix,
+            including_children,
+            leaf_only,
+        )
+    }
+
+    /// Iterate the subtrie from the node at the nibble path, the prefix is in nibbles
+    pub(super) fn from_node(
+        db: &'a dyn HashDBRef<Hasher, Vec<u8>>,
+        node_ref: NodeRef,
+        path: Vec<u8>,
+        prefix: Vec<u8>,
+        including_children: bool,
+        leaf_only: bool,
+    ) -> Self {
+        SubtrieIter {
+            db,
+            prefix,
+            including_children,
+            leaf_only,
+            stack: vec![(node_ref, path)],
+        }
+    }
+
+    /// Iterate the trie nodes instead of the data of nodes, the nodes are not filtered by
+    /// `leaf_only`
+    pub fn nodes(self) -> SubtrieNodes<'a> {
+        SubtrieNodes(self)
+    }
+
+    /// Visit the next node on the stack, and return the node if it should be yielded
+    fn visit(&mut self) -> Result<Option<SubtrieNode>, Error> {
+        let (node_ref, path) = match self.stack.pop() {
+            Some(n) => n,
+            None => return Ok(None),
+        };
+        let node = load_trie_node(self.db, &node_ref, &path)?;
+        let mut node_path = path;
+        node_path.extend_from_slice(&node.partial);
+        trace!("visit node at 0x{}", nibbles_to_hex(&node_path));
+        if !match_prefix(&node_path, &self.prefix) {
+            return Ok(None);
+        }
+
+        if self.including_children || node_path.len() < self.prefix.len() {
+            for (slot, c) in node.child_refs().rev() {
+                let mut child_path = node_path.clone();
+                child_path.extend(slot);
+                if match_prefix(&child_path, &self.prefix) {
+                    self.stack.push((c.clone(), child_path));
+                }
+            }
+        }
+
+        if node_path.len() < self.prefix.len()
+            || (!self.including_children && node_path.len() != self.prefix.len())
+        {
+            return Ok(None);
+        }
+        Ok(Some(SubtrieNode {
+            path: node_path,
+            node_ref,
+            node,
+        }))
+    }
+
+    /// Visit the nodes on the stack until a node should be yielded
+    fn next_node(&mut self) -> Option<Result<SubtrieNode, Error>> {
+        while !self.stack.is_empty() {
+            match self.visit() {
+                Ok(Some(n)) => return Some(Ok(n)),
+                Ok(None) => continue,
+                Err(e) => {
+                    self.stack.clear();
+                    return Some(Err(e));
+                }
+            }
+        }
+        None
+    }
+}
+
+impl<'a> Iterator for SubtrieIter<'a> {
+    type Item = Result<(String, Data), Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            let n = match self.next_node()? {
+                Ok(n) => n,
+                Err(e) => return Some(Err(e)),
+            };
+            let leaf = n.node.child_refs().next().is_none();
+            if self.leaf_only && !leaf {
+                continue;
+            }
+            return Some(Ok((
+                nibbles_to_hex(&n.path),
+                (n.node.value.unwrap_or_default(), leaf),
+            )));
+        }
+    }
+}
+
+/// The iterator of the trie nodes in subtrie in the order of trie path
+pub struct SubtrieNodes<'a>(SubtrieIter<'a>);
+
+impl<'a> SubtrieNodes<'a> {
+    /// Iterate the storage keys and values of the nodes with value, the keys are in nibbles
+    pub fn values(self) -> impl Iterator<Item = Result<(Vec<u8>, Vec<u8>), Error>> + 'a {
+        self.filter_map(|n| match n {
+            Ok(SubtrieNode { path, node, .. }) => node.value.map(|v| Ok((path, v))),
+            Err(e) => Some(Err(e)),
+        })
+    }
+}
+
+impl<'a> Iterator for SubtrieNodes<'a> {
+    type Item = Result<SubtrieNode, Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.0.next_node()
+    }
+}
+
+/// Compare the data of a node in two states
+///
+/// The data with the same length are compared byte by byte, else the data in the after state
+/// are deemed as inserted.
+pub(super) fn diff_data(origin: Option<Data>, after: Option<Data>) -> DiffData {
+    match (origin, after) {
+        (Some((origin, _)), Some((after, _))) if origin.len() == after.len() => {
+            let mut node_change_status = NodeChangeStatus::Unchanged;
+            let diff = origin
+                .iter()
+                .zip(after.iter())
+                .map(|(b, a)| {
+                    if a == b {
+                        0
+                    } else {
+                        node_change_status = NodeChangeStatus::Modify;
+                        *a as i16
+                    }
+                })
+                .collect();
+            (diff, node_change_status)
+        }
+        (_, Some((after, _))) => (
+            after.iter().map(|b| *b as i16).collect(),
+            NodeChangeStatus::Insert,
+        ),
+        (Some((origin, _)), None) => (
+            origin.iter().map(|b| -(*b as i16)).collect(),
+            NodeChangeStatus::Delete,
+        ),
+        (None, None) => (Vec::new(), NodeChangeStatus::Unchanged),
+    }
+}
+
+/// The iterator of the differences of the nodes in two subtries by the trie path
+pub struct SubtrieDiffIter<I: Iterator<Item = Result<(String, Data), Error>>> {
+    origin: Peekable<I>,
+    after: Peekable<I>,
+}
+
+impl<I: Iterator<Item = Result<(String, Data), Error>>> SubtrieDiffIter<I> {
+    pub fn new(origin: I, after: I) -> Self {
+        SubtrieDiffIter {
+            origin: origin.peekable(),
+            after: after.peekable(),
+        }
+    }
+}
+
+impl<I: Iterator<Item = Result<(String, Data), Error>>> Iterator for SubtrieDiffIter<I> {
+    type Item = Result<(String, DiffData), Error>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let order = match (self.origin.peek(), self.after.peek()) {
+            (None, None) => return None,
+            (Some(Err(_)), _) | (Some(Ok(_)), None) => Ordering::Less,
+            (_, Some(Err(_))) | (None, Some(Ok(_))) => Ordering::Greater,
+            (Some(Ok(o)), Some(Ok(a))) => o.0.cmp(&a.0),
+        };
+        let (origin, after) = match order {
+            Ordering::Less => (self.origin.next(), None),
+            Ordering::Greater => (None, self.after.next()),
+            Ordering::Equal => (self.origin.next(), self.after.next()),
+        };
+        let origin = match origin.transpose() {
+            Ok(o) => o,
+            Err(e) => return Some(Err(e)),
+        };
+        let after = match after.transpose() {
+            Ok(a) => a,
+            Err(e) => return Some(Err(e)),
+        };
+        let path = origin
+            .as_ref()
+            .or(after.as_ref())
+            .map(|n| n.0.clone())
+            .unwrap_or_default();
+        Some(Ok((
+            path,
+            diff_data(origin.map(|n| n.1), after.map(|n| n.1)),
+        )))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    #[test]
+    fn test_subtrie_diff_iter() {
+        let origin = vec![
+            Ok(("26aa01".to_string(), (vec![1, 2], true))),
+            Ok(("26aa02".to_string(), (vec![3], true))),
+            Ok(("26aa04".to_string(), (vec![4], true))),
+        ];
+        let after = vec![
+            Ok(("26aa01".to_string(), (vec![1, 5], true))),
+            Ok(("26aa03".to_string(), (vec![6], true))),
+            Ok(("26aa04".to_string(), (vec![4], true))),
+        ];
+        let output = SubtrieDiffIter::new(origin.into_iter(), after.into_iter())
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(
+            output,
+            vec![
+                ("26aa01".to_string(), (vec![0, 5], NodeChangeStatus::Modify)),
+                ("26aa02".to_string(), (vec![-3], NodeChangeStatus::Delete)),
+                ("26aa03".to_string(), (vec![6], NodeChangeStatus::Insert)),
+                ("26aa04".to_string(), (vec![0], NodeChangeStatus::Unchanged)),
+            ]
+        );
+    }
+}
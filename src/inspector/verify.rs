@@ -0,0 +1,214 @@
+/// Verify the integrity of the state trie in DB
+///
+/// The whole trie is walked from the state root, every node stored by hash is hashed again and
+/// compared with the hash in its parent node, and the missing nodes, the nodes with a different
+/// hash and the undecodable nodes are reported with the nibble path and the prefix in DB.
+use hash_db::{HashDBRef, Hasher as HashDBHasher};
+use serde::Serialize;
+
+use crate::cli::ArgMatches;
+use crate::errors::Error;
+use crate::inspector::db::get_state_root_hash;
+use crate::logger::{info, warn};
+use crate::storage::{
+    decode_trie_node, nibbles_to_hex, nibbles_to_prefix, setup_db_connection, Hasher, NodeRef,
+    SimpleTrie,
+};
+
+/// Log the progress after the number of nodes are verified
+const PROGRESS_NODES: usize = 100_000;
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+pub enum IssueKind {
+    /// The node is not in DB
+    Missing,
+    /// The hash of node data is not the hash in its parent node
+    HashMismatch,
+    /// The node data can not be decoded
+    Undecodable,
+}
+
+/// The problem of a trie node
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct TrieIssue {
+    pub kind: IssueKind,
+    /// The nibble path to the node in hex
+    pub path: String,
+    /// The prefix of the node key in DB in hex
+    pub prefix: String,
+    /// The hash of the node in its parent node, empty for the inline node
+    pub hash: String,
+    /// The hash of the node data in DB if it is different
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub actual_hash: Option<String>,
+}
+
+/// The result of verifying a state trie
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub struct VerifyReport {
+    /// The state root hash in hex
+    pub root: String,
+    /// The number of reachable nodes stored by hash
+    pub nodes: usize,
+    /// The number of reachable nodes inlined in their parent nodes
+    pub inline_nodes: usize,
+    /// The bytes of reachable nodes stored by hash
+    pub bytes: usize,
+    pub issues: Vec<TrieIssue>,
+}
+
+/// Walk the whole trie from the state root, and verify every reachable node
+pub fn verify_trie(db: &dyn HashDBRef<Hasher, Vec<u8>>, state_root_hash: [u8; 32]) -> VerifyReport {
+    let mut report = VerifyReport {
+        root: format!("0x{}", hex::encode(state_root_hash)),
+        ..Default::default()
+    };
+    let mut next_progress = PROGRESS_NODES;
+    let mut stack = vec![(NodeRef::Hash(state_root_hash), Vec::new())];
+    while let Some((node_ref, path)) = stack.pop() {
+        let (prefix, padded) = nibbles_to_prefix(&path);
+        let issue = |kind, hash: &[u8], actual_hash: Option<String>| {
+            let mut db_prefix = prefix.clone();
+            db_prefix.extend(padded);
+            TrieIssue {
+                kind,
+                path: nibbles_to_hex(&path),
+                prefix: hex::encode(db_prefix),
+                hash: hex::encode(hash),
+                actual_hash,
+            }
+        };
+
+        let data = match &node_ref {
+            NodeRef::Hash(h) => {
+                let data = match db.get(h, (&prefix, padded)) {
+                    Some(d) => d,
+                    None => {
+                        warn!(
+                            "node 0x{} is missing at 0x{}",
+                            hex::encode(h),
+                            nibbles_to_hex(&path)
+                        );
+                        report.issues.push(issue(IssueKind::Missing, h, None));
+                        continue;
+                    }
+                };
+                report.nodes += 1;
+                report.bytes += data.len();
+                if report.nodes == next_progress {
+                    next_progress += PROGRESS_NODES;
+                    info!(
+                        "{} nodes, {} bytes are verified",
+                        report.nodes, report.bytes
+                    );
+                }
+                let actual_hash = Hasher::hash(&data);
+                if actual_hash != *h {
+                    warn!(
+                        "node 0x{} has a different hash at 0x{}",
+                        hex::encode(h),
+                        nibbles_to_hex(&path)
+                    );
+                    report.issues.push(issue(
+                        IssueKind::HashMismatch,
+                        h,
+                        Some(hex::encode(actual_hash)),
+                    ));
+                }
+                data
+            }
+            NodeRef::Inline(d) => {
+                report.inline_nodes += 1;
+                d.clone()
+            }
+        };
+
+        let node = match decode_trie_node(&data, &path) {
+            Ok(n) => n,
+            Err(e) => {
+                warn!("{}", e);
+                let hash = match &node_ref {
+                    NodeRef::Hash(h) => h.to_vec(),
+                    NodeRef::Inline(_) => Vec::new(),
+                };
+                report
+                    .issues
+                    .push(issue(IssueKind::Undecodable, &hash, None));
+                continue;
+            }
+        };
+        let mut node_path = path.clone();
+        node_path.extend_from_slice(&node.partial);
+        for (slot, c) in node.child_refs().rev() {
+            let mut child_path = node_path.clone();
+            child_path.extend(slot);
+            stack.push((c.clone(), child_path));
+        }
+    }
+    report
+}
+
+pub fn db_verify_app(matches: ArgMatches) -> Result<(), Error> {
+    let db_path = matches.value_of("path").expect("db path is required");
+    let simple_trie = SimpleTrie {
+        db: setup_db_connection(db_path)?,
+    };
+    let state_root_hash = get_state_root_hash(&matches, "root hash", "block", &simple_trie.db)?;
+
+    info!("SSI Version: {}", env!("CARGO_PKG_VERSION"));
+    info!("DB path: {}", db_path);
+    info!("State root hash: {:?}", state_root_hash);
+
+    let report = verify_trie(&simple_trie, state_root_hash);
+    println!("{}", serde_json::to_value(&report)?);
+    if report.issues.is_empty() {
+        Ok(())
+    } else {
+        Err(Error::DBDataIncorrect(
+            format!("state root 0x{}", hex::encode(state_root_hash)),
+            format!("{} issues are found in the trie", report.issues.len()),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::tests::insert_trie;
+    use crate::storage::{load_trie_node, MemoryTrie};
+    use hash_db::HashDB;
+
+    #[test]
+    fn test_verify_trie() {
+        let mut db = MemoryTrie::default();
+        let root = insert_trie(
+            &mut db,
+            &[(b"ssi", &[1; 40]), (b"ssj", b"two"), (b"zz", &[3; 40])],
+        );
+        let report = verify_trie(&db, root);
+        assert_eq!((report.nodes, report.inline_nodes), (4, 1));
+        assert!(report.issues.is_empty());
+
+        let leaf_hash = match &load_trie_node(&db, &NodeRef::Hash(root), &[])
+            .unwrap()
+            .children[10]
+        {
+            Some(NodeRef::Hash(h)) => *h,
+            _ => panic!("the leaf of zz in slot a is stored by hash"),
+        };
+        let mut leaf = HashDBRef::get(&db, &leaf_hash, (&[], None)).unwrap();
+        *leaf.last_mut().unwrap() = b'x';
+        db.emplace(leaf_hash, (&[], None), leaf);
+        let report = verify_trie(&db, root);
+        assert_eq!(report.issues.len(), 1);
+        assert_eq!(report.issues[0].kind, IssueKind::HashMismatch);
+        assert_eq!(report.issues[0].path, "7a");
+        assert_eq!(report.issues[0].prefix, "7a");
+
+        db.remove(&leaf_hash, (&[], None));
+        let report = verify_trie(&db, root);
+        assert_eq!(report.nodes, 3);
+        assert_eq!(report.issues[0].kind, IssueKind::Missing);
+        assert_eq!(report.issues[0].hash, hex::encode(leaf_hash));
+    }
+}
@@ -0,0 +1,43 @@
+//! Substrate Storage Inspector
+//!
+//! The library inspects the trie nodes in the RocksDB of Substrate client without the runtime.
+//!
+//! - `build_storage_key_hash`: build the storage key hash from pallet, field and map keys
+//! - `get_subtrie_node`: list the exactly trie node or the subtrie nodes of the storage key
+//! - `get_child_trie_node`: list the nodes of a default child trie
+//! - `get_subtrie_diff`: compare the subtrie nodes of two states
+//! - `get_key_diff`: list the changed storage keys and values of two states
+//! - `walk_key_diff`: pass the changed storage keys and values of two states as they are found
+//! - `get_storage_proof`: generate the storage proof of storage keys
+//! - `verify_storage_proof`: verify the storage proof and get the proven values
+//! - `verify_trie`: verify the integrity of the state trie
+//! - `get_runtime_code`: get the runtime wasm blob at a state root
+//! - `write_raw_storage`: write the state as the raw genesis storage of chain spec
+//! - `storage_key_semantic_decode`: decode the storage key to pallet, field and keys
+//!
+//! The `read_*` variants of the functions take the opened DB instead of the DB path, so several
+//! lookups share the same DB handle.
+//!
+//! The runtime metadata and type definitions for decoding are loaded by `load_metadata` and
+//! `load_types`.
+pub mod cli;
+pub mod codec;
+pub mod errors;
+pub mod inspector;
+pub mod logger;
+pub mod storage;
+
+pub use codec::{
+    load_metadata, load_types, set_ss58_prefix, storage_key_semantic_decode, storage_value_decode,
+    StorageHasher,
+};
+pub use errors::Error;
+pub use inspector::{
+    build_storage_key_hash, child_trie_root, get_block_state_root, get_child_trie_node,
+    get_key_diff, get_runtime_code, get_storage_proof, get_subtrie_diff, get_subtrie_node,
+    read_block_state_root, read_child_trie_node, read_runtime_code, read_storage_proof,
+    verify_storage_proof, verify_trie, walk_key_diff, write_raw_storage, Data, DiffData,
+    DiffRecord, KeyDiffData, KeyDiffRecord, KeyDiffSink, MapKey, NodeChangeStatus, NodeRecord,
+    OutputFormat, ProofRecord, ProvenValue, Record, RecordWriter, SemanticKey, VerifyReport,
+};
+pub use storage::{BlockId, KeyspacedTrie, MemoryTrie};
@@ -1,42 +1,84 @@
 use std::env::args_os;
+use std::process;
 
-mod logger;
-use logger::{init_logger, Logger};
-
-mod storage;
-
-mod cli;
-use cli::parse_args;
-
-mod errors;
-
-mod codec;
-
-mod inspector;
-use inspector::{db_diff_app, db_inspect_app, decode_storage_key, stream_inspect_app};
+use ssi::cli::{parse_args, subcommand_matches, ArgMatches};
+use ssi::inspector::{
+    db_browse_app, db_diff_app, db_export_app, db_extract_code_app, db_inspect_app,
+    db_key_diff_app, db_proof_app, db_verify_app, decode_storage_key, stream_inspect_app,
+    verify_proof_app,
+};
+use ssi::logger::{init_logger, Logger};
+use ssi::{load_metadata, load_types, set_ss58_prefix, Error};
 
 static LOGGER: Logger = Logger;
 
+/// Set up the SS58 prefix, runtime metadata and type definitions used to decode keys and values
+fn setup_codec(matches: &ArgMatches) -> Result<(), Error> {
+    if let Some(prefix) = matches.value_of("ss58 prefix") {
+        set_ss58_prefix(prefix.parse::<u8>().map_err(|e| {
+            Error::OptionValueIncorrect("ss58 prefix".to_string(), format!("{}", e))
+        })?);
+    }
+    if let Some(metadata) = matches.value_of("metadata") {
+        load_metadata(metadata)?;
+        if let Some(types) = matches.value_of("types") {
+            load_types(types)?;
+        }
+    }
+    Ok(())
+}
+
 fn main() {
-    let matches = parse_args(args_os());
+    let (subcommand, matches) = subcommand_matches(parse_args(args_os()));
     init_logger(&LOGGER, matches.value_of("log").unwrap_or("error"));
 
-    let f = if matches.is_present("decode storage key") {
+    if let Err(e) = setup_codec(&matches) {
+        eprintln!("{}", e);
+        process::exit(1);
+    }
+
+    let f = if let Some(subcommand) = subcommand {
+        match subcommand.as_str() {
+            "extract-code" => db_extract_code_app,
+            "proof" => db_proof_app,
+            _ => unreachable!("subcommand {} is not defined", subcommand),
+        }
+    } else if matches.is_present("decode storage key") {
         if matches.is_present("storage key") {
             decode_storage_key
         } else {
             stream_inspect_app
         }
-    } else if matches.is_present("root hash diff") && matches.is_present("path") {
-        db_diff_app
+    } else if matches.is_present("verify proof") {
+        verify_proof_app
+    } else if matches.is_present("check trie") && matches.is_present("path") {
+        db_verify_app
+    } else if matches.is_present("extract code") && matches.is_present("path") {
+        db_extract_code_app
+    } else if matches.is_present("export") && matches.is_present("path") {
+        db_export_app
+    } else if matches.is_present("browse") && matches.is_present("path") {
+        db_browse_app
+    } else if matches.is_present("proof") && matches.is_present("path") {
+        db_proof_app
+    } else if (matches.is_present("root hash diff") || matches.is_present("block diff"))
+        && matches.is_present("path")
+    {
+        if matches.is_present("key diff") {
+            db_key_diff_app
+        } else {
+            db_diff_app
+        }
     } else if matches.is_present("path") {
         db_inspect_app
     } else {
-        println!("uncorrect usage: -h or --help to learn more");
-        return;
+        eprintln!("uncorrect usage: -h or --help to learn more");
+        process::exit(1);
     };
 
+    // the error exits with non-zero status, so the scripts can detect the failure
     if let Err(e) = f(matches) {
-        println!("{}", e);
+        eprintln!("{}", e);
+        process::exit(1);
     }
 }
@@ -1,29 +1,48 @@
 /// Storage
 /// implement import trait to read the storage,
 /// such that this tool can ultilize the TireNodeIterate
+use std::collections::HashMap;
+
+use codec::Decode;
 use hash_db::{AsHashDB, HashDB, HashDBRef, Hasher as HashDBHasher, Prefix};
-use rocksdb::{IteratorMode, Options, DB};
-use sp_trie::node_codec::NodeCodec;
-use trie_db::TrieLayout;
+use rocksdb::{ColumnFamily, Options, DB};
+use sp_trie::{node_codec::NodeCodec, StorageProof};
+use trie_db::{
+    node::{NodeHandlePlan, NodePlan},
+    NodeCodec as NodeCodecT, TrieLayout,
+};
 
-use crate::logger::{debug, trace};
+use crate::errors::Error;
+use crate::logger::{debug, trace, warn};
 
 pub mod blake2 {
     use hash_db::Hasher;
+    use sp_core::hashing::blake2_256;
     use std::hash::Hasher as StdHasherTrait;
 
+    /// The std hasher for the keys which are hash already, the first 8 bytes of the hash are used,
+    /// such that the trie nodes can be kept in std `HashMap` without hashing again.
+    /// The data shorter than a hash is not used.
     #[derive(Default)]
-    pub struct StdHasher {}
+    pub struct StdHasher {
+        prefix: u64,
+    }
+
     impl StdHasherTrait for StdHasher {
         #[inline]
         fn finish(&self) -> u64 {
-            unimplemented!("finish of StdHasherTrait unimplement");
+            self.prefix
         }
 
         #[inline]
-        #[allow(unused_assignments)]
-        fn write(&mut self, _bytes: &[u8]) {
-            unimplemented!("write of StdHasherTrait unimplement");
+        fn write(&mut self, bytes: &[u8]) {
+            // the length prefix written before the hash is skipped
+            if bytes.len() < Blake2Hasher::LENGTH {
+                return;
+            }
+            let mut prefix = [0u8; 8];
+            prefix.copy_from_slice(&bytes[..8]);
+            self.prefix ^= u64::from_le_bytes(prefix);
         }
     }
 
@@ -35,21 +54,84 @@ pub mod blake2 {
         type StdHasher = StdHasher;
         const LENGTH: usize = 32;
 
-        fn hash(_x: &[u8]) -> Self::Out {
-            unimplemented!("hash of Hasher unimplement");
+        fn hash(x: &[u8]) -> Self::Out {
+            blake2_256(x)
         }
     }
 }
 
-type Hash = [u8; 32];
+pub type Hash = [u8; 32];
 pub type Hasher = crate::storage::blake2::Blake2Hasher;
 
-pub struct SimpleTrie<'a> {
+/// The columns of Substrate client DB, the column family in RocksDB is named as `col{column}`
+pub mod columns {
+    pub const META: u32 = 0;
+    pub const STATE: u32 = 1;
+    pub const STATE_META: u32 = 2;
+    /// maps hashes to lookup keys and numbers to canon hashes.
+    pub const KEY_LOOKUP: u32 = 3;
+    pub const HEADER: u32 = 4;
+    pub const BODY: u32 = 5;
+    pub const JUSTIFICATION: u32 = 6;
+    pub const CHANGES_TRIE: u32 = 7;
+    pub const AUX: u32 = 8;
+    /// Offchain workers local storage
+    pub const OFFCHAIN: u32 = 9;
+    pub const CACHE: u32 = 10;
+
+    /// The columns required to inspect the state
+    pub const REQUIRED: [u32; 3] = [STATE, KEY_LOOKUP, HEADER];
+
+    /// The column family name in RocksDB
+    pub fn cf_name(column: u32) -> String {
+        format!("col{}", column)
+    }
+
+    /// The meaning of column
+    pub fn meaning(column: u32) -> &'static str {
+        match column {
+            META => "META",
+            STATE => "STATE",
+            STATE_META => "STATE_META",
+            KEY_LOOKUP => "KEY_LOOKUP",
+            HEADER => "HEADER",
+            BODY => "BODY",
+            JUSTIFICATION => "JUSTIFICATION",
+            CHANGES_TRIE => "CHANGES_TRIE",
+            AUX => "AUX",
+            OFFCHAIN => "OFFCHAIN",
+            CACHE => "CACHE",
+            _ => "UNKNOWN",
+        }
+    }
+}
+
+/// Get the column family handle of the Substrate column
+pub fn column_handle(db: &DB, column: u32) -> Result<&ColumnFamily, Error> {
+    db.cf_handle(&columns::cf_name(column)).ok_or_else(|| {
+        Error::DBDataIncorrect(
+            format!("{} column", columns::meaning(column)),
+            format!("column family {} is not opened", columns::cf_name(column)),
+        )
+    })
+}
+
+/// The key of trie node in DB, prefix ++ hash
+fn prefixed_key(key: &Hash, prefix: Prefix) -> Vec<u8> {
+    let mut k = prefix.0.to_vec();
+    if let Some(p) = prefix.1 {
+        k.push(p);
+    }
+    k.extend_from_slice(key);
+    k
+}
+
+/// The trie nodes in the STATE column of DB
+pub struct SimpleTrie {
     pub db: DB,
-    pub cfs: Vec<&'a str>,
 }
 
-impl<'a> AsHashDB<Hasher, Vec<u8>> for SimpleTrie<'a> {
+impl AsHashDB<Hasher, Vec<u8>> for SimpleTrie {
     fn as_hash_db(&self) -> &dyn hash_db::HashDB<Hasher, Vec<u8>> {
         &*self
     }
@@ -59,56 +141,13 @@ impl<'a> AsHashDB<Hasher, Vec<u8>> for SimpleTrie<'a> {
     }
 }
 
-impl<'a> HashDB<Hasher, Vec<u8>> for SimpleTrie<'a> {
+impl HashDB<Hasher, Vec<u8>> for SimpleTrie {
     fn get(&self, key: &Hash, prefix: Prefix) -> Option<Vec<u8>> {
-        trace!("get prefix: {:?}, key({}): {:?}", prefix, key.len(), key);
-        let key: Vec<u8> = if !prefix.0.is_empty() || prefix.1.is_some() {
-            let mut k = if !prefix.0.is_empty() {
-                prefix.0.to_vec()
-            } else {
-                Vec::<u8>::new()
-            };
-            if let Some(p) = prefix.1 {
-                k.push(p);
-            }
-            k.append(&mut key.to_vec());
-            trace!("Prefixed key: {:?}", k);
-            k
-        } else {
-            trace!("key: {:?}", key);
-            key.to_vec()
-        };
-        for cf in self.cfs.clone() {
-            let h = self.db.cf_handle(cf).unwrap();
-            if let Some(v) = self.db.get_cf(h, &key).unwrap() {
-                return Some(v);
-            }
-        }
-        None
+        HashDBRef::get(self, key, prefix)
     }
 
     fn contains(&self, hash: &Hash, prefix: Prefix) -> bool {
-        trace!(
-            "contains prefix: {:?} key({}): {:?}",
-            prefix,
-            hash.len(),
-            hash
-        );
-        let key: Vec<u8> = if let Some(p) = prefix.1 {
-            let mut k = vec![p];
-            k.append(&mut hash.to_vec());
-            k
-        } else {
-            hash.to_vec()
-        };
-        debug!("key: {:?}", key);
-        for cf in self.cfs.clone() {
-            let h = self.db.cf_handle(cf).unwrap();
-            if self.db.get_cf(h, &hash).unwrap().is_some() {
-                return true;
-            }
-        }
-        false
+        HashDBRef::contains(self, hash, prefix)
     }
 
     fn insert(&mut self, _prefix: Prefix, _value: &[u8]) -> Hash {
@@ -124,32 +163,10 @@ impl<'a> HashDB<Hasher, Vec<u8>> for SimpleTrie<'a> {
     }
 }
 
-impl<'a> HashDBRef<Hasher, Vec<u8>> for SimpleTrie<'a> {
+impl HashDBRef<Hasher, Vec<u8>> for SimpleTrie {
     fn get(&self, key: &Hash, prefix: Prefix) -> Option<Vec<u8>> {
         trace!("get prefix: {:?}, key({}): {:?}", prefix, key.len(), key);
-        let key: Vec<u8> = if !prefix.0.is_empty() || prefix.1.is_some() {
-            let mut k = if !prefix.0.is_empty() {
-                prefix.0.to_vec()
-            } else {
-                Vec::<u8>::new()
-            };
-            if let Some(p) = prefix.1 {
-                k.push(p);
-            }
-            k.append(&mut key.to_vec());
-            trace!("Prefixed key: {:?}", k);
-            k
-        } else {
-            trace!("key: {:?}", key);
-            key.to_vec()
-        };
-        for cf in self.cfs.clone() {
-            let h = self.db.cf_handle(cf).unwrap();
-            if let Some(v) = self.db.get_cf(h, &key).unwrap() {
-                return Some(v);
-            }
-        }
-        None
+        raw_query(&self.db, prefix, *key)
     }
 
     fn contains(&self, hash: &Hash, prefix: Prefix) -> bool {
@@ -159,21 +176,100 @@ impl<'a> HashDBRef<Hasher, Vec<u8>> for SimpleTrie<'a> {
             hash.len(),
             hash
         );
-        let key: Vec<u8> = if let Some(p) = prefix.1 {
-            let mut k = vec![p];
-            k.append(&mut hash.to_vec());
-            k
-        } else {
-            hash.to_vec()
-        };
-        trace!("key: {:?}", key);
-        for cf in self.cfs.clone() {
-            let h = self.db.cf_handle(cf).unwrap();
-            if self.db.get_cf(h, &key).unwrap().is_some() {
-                return true;
-            }
+        HashDBRef::get(self, hash, prefix).is_some()
+    }
+}
+
+/// The trie nodes in memory, the nodes are keyed by the hash of node without prefix,
+/// such that the nodes of a storage proof can be looked up without the DB
+#[derive(Default)]
+pub struct MemoryTrie {
+    nodes: HashMap<Hash, Vec<u8>>,
+}
+
+impl From<StorageProof> for MemoryTrie {
+    fn from(proof: StorageProof) -> Self {
+        let mut trie = MemoryTrie::default();
+        for node in proof.iter_nodes() {
+            HashDB::insert(&mut trie, (&[], None), &node);
         }
-        false
+        trie
+    }
+}
+
+impl AsHashDB<Hasher, Vec<u8>> for MemoryTrie {
+    fn as_hash_db(&self) -> &dyn hash_db::HashDB<Hasher, Vec<u8>> {
+        self
+    }
+
+    fn as_hash_db_mut<'b>(&'b mut self) -> &'b mut (dyn HashDB<Hasher, Vec<u8>> + 'b) {
+        &mut *self
+    }
+}
+
+impl HashDB<Hasher, Vec<u8>> for MemoryTrie {
+    fn get(&self, key: &Hash, prefix: Prefix) -> Option<Vec<u8>> {
+        HashDBRef::get(self, key, prefix)
+    }
+
+    fn contains(&self, hash: &Hash, prefix: Prefix) -> bool {
+        HashDBRef::contains(self, hash, prefix)
+    }
+
+    fn insert(&mut self, prefix: Prefix, value: &[u8]) -> Hash {
+        let key = Hasher::hash(value);
+        self.emplace(key, prefix, value.to_vec());
+        key
+    }
+
+    fn emplace(&mut self, key: Hash, _prefix: Prefix, value: Vec<u8>) {
+        self.nodes.insert(key, value);
+    }
+
+    fn remove(&mut self, key: &Hash, _prefix: Prefix) {
+        self.nodes.remove(key);
+    }
+}
+
+impl HashDBRef<Hasher, Vec<u8>> for MemoryTrie {
+    fn get(&self, key: &Hash, _prefix: Prefix) -> Option<Vec<u8>> {
+        self.nodes.get(key).cloned()
+    }
+
+    fn contains(&self, hash: &Hash, _prefix: Prefix) -> bool {
+        self.nodes.contains_key(hash)
+    }
+}
+
+/// The trie nodes of a child trie, the child id is the keyspace before the prefix of node key in DB
+pub struct KeyspacedTrie<'a> {
+    db: &'a dyn HashDBRef<Hasher, Vec<u8>>,
+    keyspace: Vec<u8>,
+}
+
+impl<'a> KeyspacedTrie<'a> {
+    pub fn new(db: &'a dyn HashDBRef<Hasher, Vec<u8>>, keyspace: &[u8]) -> Self {
+        KeyspacedTrie {
+            db,
+            keyspace: keyspace.to_vec(),
+        }
+    }
+
+    fn keyspaced_prefix(&self, prefix: Prefix) -> Vec<u8> {
+        let mut keyspaced = self.keyspace.clone();
+        keyspaced.extend_from_slice(prefix.0);
+        keyspaced
+    }
+}
+
+impl<'a> HashDBRef<Hasher, Vec<u8>> for KeyspacedTrie<'a> {
+    fn get(&self, key: &Hash, prefix: Prefix) -> Option<Vec<u8>> {
+        self.db.get(key, (&self.keyspaced_prefix(prefix), prefix.1))
+    }
+
+    fn contains(&self, hash: &Hash, prefix: Prefix) -> bool {
+        self.db
+            .contains(hash, (&self.keyspaced_prefix(prefix), prefix.1))
     }
 }
 
@@ -184,42 +280,90 @@ impl<H: HashDBHasher> TrieLayout for Layout<H> {
     type Hash = H;
     type Codec = NodeCodec<Self::Hash>;
 }
-pub fn setup_db_connection(db_path: &str) -> (DB, Vec<&'static str>) {
+/// Open the Substrate RocksDB with the column families on disk,
+/// the DB should have the columns required to inspect the state
+pub fn setup_db_connection(db_path: &str) -> Result<DB, Error> {
     let opts = Options::default();
-    let cfs = vec![
-        "default", "col0", "col1", "col2", "col3", "col4", "col5", "col6", "col7", "col8", "col9",
-        "col10",
-    ];
+    let cfs = DB::list_cf(&opts, db_path)
+        .map_err(|e| Error::DBDataIncorrect(db_path.to_string(), e.into_string()))?;
+    for cf in cfs.iter() {
+        if let Some(column) = cf.strip_prefix("col").and_then(|c| c.parse().ok()) {
+            debug!("column family {}: {}", cf, columns::meaning(column));
+        } else {
+            debug!("column family {}", cf);
+        }
+    }
+    for column in columns::REQUIRED.iter() {
+        if !cfs.contains(&columns::cf_name(*column)) {
+            return Err(Error::DBDataIncorrect(
+                db_path.to_string(),
+                format!(
+                    "the {} column ({}) is not found in column families {:?}, the DB is not a Substrate RocksDB",
+                    columns::meaning(*column),
+                    columns::cf_name(*column),
+                    cfs
+                ),
+            ));
+        }
+    }
+    DB::open_cf_for_read_only(&opts, db_path, cfs, false)
+        .map_err(|e| Error::DBDataIncorrect(db_path.to_string(), e.into_string()))
+}
 
-    // TODO handle this unwarp
-    let db = DB::open_cf_for_read_only(&opts, db_path, cfs.clone(), false).unwrap();
-    (db, cfs)
+/// The block identifier used to find a block in the DB
+pub enum BlockId {
+    Number(u32),
+    Hash(Hash),
 }
 
-pub fn raw_query(db: &DB, cfs: &[&str], prefix: Prefix, node_key: [u8; 32]) -> Option<Box<[u8]>> {
-    let key: Vec<u8> = if !prefix.0.is_empty() || prefix.1.is_some() {
-        let mut k = if !prefix.0.is_empty() {
-            prefix.0.to_vec()
-        } else {
-            Vec::<u8>::new()
-        };
-        if let Some(p) = prefix.1 {
-            k.push(p);
+/// The leading fields of substrate block header, the digest is not decoded
+#[derive(Decode)]
+pub struct BlockHeader {
+    pub parent_hash: Hash,
+    #[codec(compact)]
+    pub number: u32,
+    pub state_root: Hash,
+    pub extrinsics_root: Hash,
+}
+
+/// Get the block header from the HEADER column,
+/// the lookup key, block number (big endian) ++ block hash, is read from KEY_LOOKUP column
+pub fn get_block_header(db: &DB, block_id: &BlockId) -> Result<Option<BlockHeader>, Error> {
+    let lookup_cf = column_handle(db, columns::KEY_LOOKUP)?;
+    let header_cf = column_handle(db, columns::HEADER)?;
+
+    let lookup_key = match block_id {
+        BlockId::Number(n) => db.get_cf(lookup_cf, n.to_be_bytes()),
+        BlockId::Hash(h) => db.get_cf(lookup_cf, h),
+    }
+    .map_err(|e| Error::DBDataIncorrect("KEY_LOOKUP column".to_string(), e.into_string()))?;
+    trace!("block lookup key: {:?}", lookup_key);
+
+    if let Some(lookup_key) = lookup_key {
+        let encoded_header = db
+            .get_cf(header_cf, lookup_key)
+            .map_err(|e| Error::DBDataIncorrect("HEADER column".to_string(), e.into_string()))?;
+        if let Some(encoded_header) = encoded_header {
+            return BlockHeader::decode(&mut &encoded_header[..])
+                .map(Some)
+                .map_err(|e| Error::DBDataIncorrect("block header".to_string(), format!("{}", e)));
         }
-        k.append(&mut node_key.to_vec());
-        k
-    } else {
-        node_key.to_vec()
-    };
-    for cf in cfs.iter() {
-        let h = db.cf_handle(cf).unwrap();
-        for (k, v) in db.iterator_cf(h, IteratorMode::Start) {
-            if *k == key[..] {
-                return Some(v);
-            }
+    }
+    Ok(None)
+}
+
+/// Get the trie node data from the STATE column by a point lookup of the prefixed node key
+pub fn raw_query(db: &DB, prefix: Prefix, node_key: Hash) -> Option<Vec<u8>> {
+    let key = prefixed_key(&node_key, prefix);
+    trace!("Prefixed key: {:?}", key);
+    let h = column_handle(db, columns::STATE).ok()?;
+    match db.get_cf(h, &key) {
+        Ok(v) => v,
+        Err(e) => {
+            warn!("fail to get the trie node {:?}: {}", key, e);
+            None
         }
     }
-    None
 }
 
 /// Helper function for char to children nodes index
@@ -251,3 +395,320 @@ pub fn map_pos_to_char(p: usize) -> char {
         _ => panic!("hex string uncorrect"),
     }
 }
+
+/// Helper function to pack the nibble path into the prefix of node key in DB
+pub fn nibbles_to_prefix(nibbles: &[u8]) -> (Vec<u8>, Option<u8>) {
+    let mut packed: Vec<u8> = nibbles
+        .chunks(2)
+        .map(|c| {
+            if c.len() == 2 {
+                c[0] << 4 | c[1]
+            } else {
+                c[0] << 4
+            }
+        })
+        .collect();
+    if nibbles.len() % 2 == 1 {
+        let last = packed.pop();
+        (packed, last)
+    } else {
+        (packed, None)
+    }
+}
+
+/// Helper function for nibble path to hex string
+pub fn nibbles_to_hex(nibbles: &[u8]) -> String {
+    nibbles
+        .iter()
+        .map(|n| map_pos_to_char(*n as usize))
+        .collect()
+}
+
+/// The reference to a trie node,
+/// the hash of a node stored in DB or the data of a node inlined in the parent node
+#[derive(Clone, Debug, PartialEq)]
+pub enum NodeRef {
+    Hash(Hash),
+    Inline(Vec<u8>),
+}
+
+impl NodeRef {
+    fn from_plan(plan: &NodeHandlePlan, data: &[u8]) -> Self {
+        match plan {
+            NodeHandlePlan::Hash(r) => {
+                let mut h: Hash = Default::default();
+                h.copy_from_slice(&data[r.clone()]);
+                NodeRef::Hash(h)
+            }
+            NodeHandlePlan::Inline(r) => NodeRef::Inline(data[r.clone()].to_vec()),
+        }
+    }
+}
+
+/// The kind of trie node in the node codec
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum NodeKind {
+    Empty,
+    Leaf,
+    Extension,
+    Branch,
+    NibbledBranch,
+}
+
+/// The decoded trie node, the nibbles of partial key, the value and the children
+pub struct TrieNode {
+    pub kind: NodeKind,
+    pub partial: Vec<u8>,
+    pub value: Option<Vec<u8>>,
+    pub children: Vec<Option<NodeRef>>,
+    /// The child of extension node, which is at the end of the partial key without a child slot
+    pub extension_child: Option<NodeRef>,
+}
+
+impl TrieNode {
+    /// The references to the children with the child slot, the slot of extension child is `None`,
+    /// so the path to a child is the node path followed by the slot
+    pub fn child_refs(&self) -> impl DoubleEndedIterator<Item = (Option<u8>, &NodeRef)> {
+        self.extension_child.iter().map(|c| (None, c)).chain(
+            self.children
+                .iter()
+                .enumerate()
+                .filter_map(|(idx, c)| c.as_ref().map(|c| (Some(idx as u8), c))),
+        )
+    }
+}
+
+/// Load the trie node from DB or from the inline data, and decode it.
+/// The `path` is the nibble path from root to the node, which is the prefix of node key in DB.
+pub fn load_trie_node(
+    db: &dyn HashDBRef<Hasher, Vec<u8>>,
+    node_ref: &NodeRef,
+    path: &[u8],
+) -> Result<TrieNode, Error> {
+    let data = match node_ref {
+        NodeRef::Hash(h) => {
+            let (prefix, padded) = nibbles_to_prefix(path);
+            db.get(h, (&prefix, padded)).ok_or_else(|| {
+                Error::DBDataIncorrect(
+                    format!("trie node 0x{}", hex::encode(h)),
+                    format!("node is missing at path 0x{}", nibbles_to_hex(path)),
+                )
+            })?
+        }
+        NodeRef::Inline(d) => d.clone(),
+    };
+    decode_trie_node(&data, path)
+}
+
+/// Decode the data of trie node, the `path` is the nibble path from root to the node
+pub fn decode_trie_node(data: &[u8], path: &[u8]) -> Result<TrieNode, Error> {
+    let plan = <Layout<Hasher> as TrieLayout>::Codec::decode_plan(data).map_err(|e| {
+        Error::DBDataIncorrect(
+            format!("trie node at path 0x{}", nibbles_to_hex(path)),
+            format!("{:?}", e),
+        )
+    })?;
+    let node = match plan {
+        NodePlan::Empty => TrieNode {
+            kind: NodeKind::Empty,
+            partial: vec![],
+            value: None,
+            children: vec![],
+            extension_child: None,
+        },
+        NodePlan::Leaf { partial, value } => TrieNode {
+            kind: NodeKind::Leaf,
+            partial: partial.build(data).iter().collect(),
+            value: Some(data[value].to_vec()),
+            children: vec![],
+            extension_child: None,
+        },
+        // The extension node is not used in substrate
+        NodePlan::Extension { partial, child } => TrieNode {
+            kind: NodeKind::Extension,
+            partial: partial.build(data).iter().collect(),
+            value: None,
+            children: vec![],
+            extension_child: Some(NodeRef::from_plan(&child, data)),
+        },
+        NodePlan::Branch { value, children } => TrieNode {
+            kind: NodeKind::Branch,
+            partial: vec![],
+            value: value.map(|r| data[r].to_vec()),
+            children: children
+                .iter()
+                .map(|c| c.as_ref().map(|c| NodeRef::from_plan(c, data)))
+                .collect(),
+            extension_child: None,
+        },
+        NodePlan::NibbledBranch {
+            partial,
+            value,
+            children,
+        } => TrieNode {
+            kind: NodeKind::NibbledBranch,
+            partial: partial.build(data).iter().collect(),
+            value: value.map(|r| data[r].to_vec()),
+            children: children
+                .iter()
+                .map(|c| c.as_ref().map(|c| NodeRef::from_plan(c, data)))
+                .collect(),
+            extension_child: None,
+        },
+    };
+    Ok(node)
+}
+
+/// Look up the value of the storage key from the state root, the `key` is in nibbles.
+/// `None` is returned if the key is not in the trie.
+pub fn lookup_value(
+    db: &dyn HashDBRef<Hasher, Vec<u8>>,
+    state_root_hash: Hash,
+    key: &[u8],
+) -> Result<Option<Vec<u8>>, Error> {
+    let mut node_ref = NodeRef::Hash(state_root_hash);
+    let mut path = Vec::new();
+    loop {
+        let node = load_trie_node(db, &node_ref, &path)?;
+        path.extend_from_slice(&node.partial);
+        if !key.starts_with(&path) {
+            return Ok(None);
+        }
+        if let Some(c) = node.extension_child {
+            node_ref = c;
+            continue;
+        }
+        if key.len() == path.len() {
+            return Ok(node.value);
+        }
+        let idx = key[path.len()];
+        node_ref = match node.children.get(idx as usize).cloned().flatten() {
+            Some(c) => c,
+            None => return Ok(None),
+        };
+        path.push(idx);
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use std::cell::RefCell;
+    use trie_db::ChildReference;
+
+    type Codec = <Layout<Hasher> as TrieLayout>::Codec;
+
+    /// A nibbled branch at `0x3a` with an inline leaf `0x3a123` of "one" in slot 1, and a leaf
+    /// `0x3aabc` of "ten" stored by hash in slot a, the root node is at the zero hash
+    pub(crate) fn sample_trie() -> MemoryTrie {
+        let inline = Codec::leaf_node(((0, 0), &[0x23]), b"one");
+        let mut inline_data: Hash = Default::default();
+        inline_data[..inline.len()].copy_from_slice(&inline);
+        let mut children = vec![None; 16];
+        children[1] = Some(ChildReference::Inline(inline_data, inline.len()));
+        children[10] = Some(ChildReference::Hash([1; 32]));
+        let root = Codec::branch_node_nibbled(vec![0x3a].into_iter(), 2, children.iter(), None);
+
+        let mut trie = MemoryTrie::default();
+        trie.emplace([0; 32], (&[], None), root);
+        trie.emplace(
+            [1; 32],
+            (&[], None),
+            Codec::leaf_node(((0, 0), &[0xbc]), b"ten"),
+        );
+        trie
+    }
+
+    /// Build the trie of the key value pairs into the memory trie, and return the root hash, the
+    /// tries built into the same memory trie share the same nodes
+    pub(crate) fn insert_trie(trie: &mut MemoryTrie, pairs: &[(&[u8], &[u8])]) -> Hash {
+        use trie_db::{TrieDBMut, TrieMut};
+
+        // the empty root node is removed from the memory trie when the first value is inserted
+        let mut root = HashDB::insert(trie, (&[], None), Codec::empty_node());
+        {
+            let mut trie_mut = TrieDBMut::<Layout<Hasher>>::new(trie, &mut root);
+            for (key, value) in pairs.iter() {
+                trie_mut.insert(key, value).unwrap();
+            }
+        }
+        root
+    }
+
+    /// Build the trie of the key value pairs in a new memory trie, and return the root hash
+    pub(crate) fn memory_trie(pairs: &[(&[u8], &[u8])]) -> (MemoryTrie, Hash) {
+        let mut trie = MemoryTrie::default();
+        let root = insert_trie(&mut trie, pairs);
+        (trie, root)
+    }
+
+    /// Record the prefixes of the nodes loaded from DB
+    pub(crate) struct PrefixRecorder<'a> {
+        db: &'a dyn HashDBRef<Hasher, Vec<u8>>,
+        pub(crate) prefixes: RefCell<Vec<Vec<u8>>>,
+    }
+
+    impl<'a> PrefixRecorder<'a> {
+        pub(crate) fn new(db: &'a dyn HashDBRef<Hasher, Vec<u8>>) -> Self {
+            PrefixRecorder {
+                db,
+                prefixes: RefCell::new(Vec::new()),
+            }
+        }
+    }
+
+    impl<'a> HashDBRef<Hasher, Vec<u8>> for PrefixRecorder<'a> {
+        fn get(&self, key: &Hash, prefix: Prefix) -> Option<Vec<u8>> {
+            self.prefixes.borrow_mut().push(prefix.0.to_vec());
+            self.db.get(key, prefix)
+        }
+
+        fn contains(&self, key: &Hash, prefix: Prefix) -> bool {
+            self.db.contains(key, prefix)
+        }
+    }
+
+    #[test]
+    fn test_layout_hasher() {
+        use trie_db::{Trie, TrieDB};
+
+        let (trie, root) = memory_trie(&[(b"ssi", b"one"), (b"ssi:two", &[2; 40])]);
+        assert_eq!(
+            root,
+            Hasher::hash(&HashDBRef::get(&trie, &root, (&[], None)).unwrap())
+        );
+        let key: Vec<u8> = hex::encode(b"ssi:two")
+            .chars()
+            .map(|c| map_char_to_pos(c) as u8)
+            .collect();
+        assert_eq!(lookup_value(&trie, root, &key).unwrap(), Some(vec![2; 40]));
+        let trie_db = TrieDB::<Layout<Hasher>>::new(&trie, &root).unwrap();
+        assert_eq!(trie_db.get(b"ssi").unwrap(), Some(b"one".to_vec()));
+    }
+
+    #[test]
+    fn test_child_refs() {
+        let extension = TrieNode {
+            kind: NodeKind::Extension,
+            partial: vec![3, 10],
+            value: None,
+            children: vec![],
+            extension_child: Some(NodeRef::Hash([1; 32])),
+        };
+        let refs: Vec<_> = extension.child_refs().collect();
+        assert_eq!(refs, vec![(None, &NodeRef::Hash([1; 32]))]);
+
+        let mut children = vec![None; 16];
+        children[1] = Some(NodeRef::Inline(vec![1]));
+        children[10] = Some(NodeRef::Hash([1; 32]));
+        let branch = TrieNode {
+            kind: NodeKind::NibbledBranch,
+            partial: vec![3, 10],
+            value: None,
+            children,
+            extension_child: None,
+        };
+        let slots: Vec<_> = branch.child_refs().rev().map(|(slot, _)| slot).collect();
+        assert_eq!(slots, vec![Some(10), Some(1)]);
+    }
+}
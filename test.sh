@@ -20,6 +20,9 @@ cargo run -- -r 0x3b559d574c4a9f13e55d0256655f0f71a70a703766226f1080f80022e39c05
 cargo run -- -r 0x3b559d574c4a9f13e55d0256655f0f71a70a703766226f1080f80022e39c057d -P Balance -F Account -T //Feride ./db | jq '.[]|keys'
 cargo run -- -r 0x940a55c41ce61b2d771e82f8a6c6f4939a712a644502f5efa7c59afea0a3a67e -P Balance -F Account -T '//Feride' ./db | jq '.[]|keys'
 
+# Get the state root hash from block number
+cargo run -- -n 50 -P System -F Account ./db | jq '.[]|keys'
+
 # Get subtrie with uncomplete storage keys
 cargo run -- -r 0x3b559d574c4a9f13e55d0256655f0f71a70a703766226f1080f80022e39c057d -k 26 ./db | jq '.[]|keys'
 
@@ -35,4 +38,6 @@ cargo run -- -d .test-data/block-499135.log
 echo -e "Test diff function.."
 cargo run -- -r 0x3b559d574c4a9f13e55d0256655f0f71a70a703766226f1080f80022e39c057d -R 0x940a55c41ce61b2d771e82f8a6c6f4939a712a644502f5efa7c59afea0a3a67e -P System -F Account ./db | jq
 cargo run -- -r 0x3b559d574c4a9f13e55d0256655f0f71a70a703766226f1080f80022e39c057d -R 0x940a55c41ce61b2d771e82f8a6c6f4939a712a644502f5efa7c59afea0a3a67e -P System -F Account ./db -s | jq
+cargo run -- -n 5 -N 50 -P System -F Account -K ./db | jq
+cargo run -- -n 5 -N 50 -P System -F Account -K -s ./db | jq
 echo -e "\e[0;32m  +------------------+ \n\e[0;32m  | JSON Format Pass | \n\e[0;32m  +------------------+ \e[0m"